@@ -0,0 +1,496 @@
+#![no_std]
+
+use core::option::Option;
+use soroban_sdk::auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation};
+use soroban_sdk::token;
+use soroban_sdk::{
+    contract, contractclient, contractimpl, contracttype, symbol_short, vec, Address, Env,
+    IntoVal, Map, String, Symbol, Vec,
+};
+
+fn k_cfg() -> Symbol { symbol_short!("cfg") }
+fn k_constituents() -> Symbol { symbol_short!("cons") }
+fn k_bal() -> Symbol { symbol_short!("bal") }
+fn k_tot() -> Symbol { symbol_short!("tot") }
+
+const TOTAL_WEIGHT_BPS: u32 = 10_000;
+const MAX_CONSTITUENTS: u32 = 10;
+
+/// The bonding-curve calls the basket makes on each paper token.
+#[contractclient(name = "PaperCurveClient")]
+pub trait PaperCurve {
+    fn balance_of(env: Env, who: Address) -> i128;
+    fn payment_token(env: Env) -> Option<Address>;
+    fn quote_buy(env: Env, amount: i128) -> CurveQuote;
+    fn quote_sell(env: Env, amount: i128) -> CurveQuote;
+    fn quote_buy_for_payment(env: Env, payment: i128) -> i128;
+    fn buy(env: Env, buyer: Address, amount: i128, max_payment: i128) -> CurveQuote;
+    fn sell(env: Env, seller: Address, amount: i128, min_payment: i128) -> CurveQuote;
+    fn delisting_phase(env: Env) -> DelistPhase;
+    fn quote_redeem(env: Env, amount: i128) -> i128;
+    fn redeem(env: Env, holder: Address, amount: i128) -> i128;
+}
+
+/// Mirrors the paper token's `Quote`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct CurveQuote {
+    pub cost: i128,
+    pub price_before: i128,
+    pub price_after: i128,
+}
+
+/// Mirrors the paper token's `DelistPhase`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum DelistPhase {
+    Listed,
+    Notice,
+    Redemption,
+    Closed,
+}
+
+/// The marketplace call the basket uses to vet constituents.
+#[contractclient(name = "MarketplaceClient")]
+pub trait Marketplace {
+    fn get_listing(env: Env, paper_id: u32) -> Option<ListingRecord>;
+}
+
+/// Mirrors the marketplace's `Listing`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Listing {
+    pub paper_id: u32,
+    pub token: Address,
+    pub metadata_uri: String,
+    pub owner: Address,
+    pub is_active: bool,
+    pub created_at: u64,
+}
+
+/// Mirrors the marketplace's `ListingRecord`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct ListingRecord {
+    pub id: u32,
+    pub data: Listing,
+}
+
+/// `marketplace` is fixed at init; only tokens it lists can join the basket.
+#[derive(Clone)]
+#[contracttype]
+pub struct BasketConfig {
+    pub curator: Address,
+    pub name: String,
+    pub symbol: String,
+    pub payment_token: Address,
+    pub marketplace: Address,
+}
+
+/// A listed paper's token and its share of the basket's value, in basis
+/// points.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Constituent {
+    pub paper_id: u32,
+    pub token: Address,
+    pub weight_bps: u32,
+}
+
+#[contract]
+pub struct PapexBasket;
+
+impl PapexBasket {
+    fn read_cfg(env: &Env) -> BasketConfig {
+        env.storage()
+            .instance()
+            .get(&k_cfg())
+            .unwrap_or_else(|| panic!("basket not initialized"))
+    }
+
+    fn read_constituents(env: &Env) -> Vec<Constituent> {
+        env.storage()
+            .instance()
+            .get(&k_constituents())
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn read_balances(env: &Env) -> Map<Address, i128> {
+        env.storage()
+            .instance()
+            .get(&k_bal())
+            .unwrap_or(Map::new(env))
+    }
+
+    fn write_balances(env: &Env, balances: &Map<Address, i128>) {
+        env.storage().instance().set(&k_bal(), balances);
+    }
+
+    fn read_total_shares(env: &Env) -> i128 {
+        env.storage().instance().get(&k_tot()).unwrap_or(0)
+    }
+
+    fn write_total_shares(env: &Env, amount: i128) {
+        env.storage().instance().set(&k_tot(), &amount);
+    }
+
+    fn require_curator(env: &Env, caller: &Address) -> BasketConfig {
+        let cfg = Self::read_cfg(env);
+        caller.require_auth();
+        if *caller != cfg.curator {
+            panic!("only curator");
+        }
+        cfg
+    }
+
+    fn ensure_amount(amount: i128) {
+        if amount <= 0 {
+            panic!("amount must be positive");
+        }
+    }
+
+    /// Weights must be positive and add up to 100%, and every token must
+    /// appear once, be the token of an active marketplace listing and be
+    /// priced in the basket's payment asset. The basket approves payments to
+    /// constituents, so an arbitrary contract must never become one.
+    fn validate_constituents(env: &Env, cfg: &BasketConfig, constituents: &Vec<Constituent>) {
+        if constituents.is_empty() || constituents.len() > MAX_CONSTITUENTS {
+            panic!("invalid constituents");
+        }
+        let payment = &cfg.payment_token;
+        let marketplace = MarketplaceClient::new(env, &cfg.marketplace);
+        let mut total = 0u32;
+        for (i, c) in constituents.iter().enumerate() {
+            if c.weight_bps == 0 || constituents.iter().skip(i + 1).any(|o| o.token == c.token) {
+                panic!("invalid constituents");
+            }
+            let listed = match marketplace.get_listing(&c.paper_id) {
+                Option::Some(record) => record.data.token == c.token && record.data.is_active,
+                Option::None => false,
+            };
+            if !listed {
+                panic!("constituent not listed");
+            }
+            let asset = PaperCurveClient::new(env, &c.token).payment_token();
+            if asset != Option::Some(payment.clone()) {
+                panic!("payment asset mismatch");
+            }
+            total += c.weight_bps;
+        }
+        if total != TOTAL_WEIGHT_BPS {
+            panic!("weights must total 10000");
+        }
+    }
+
+    fn holding(env: &Env, token: &Address) -> i128 {
+        PaperCurveClient::new(env, token).balance_of(&env.current_contract_address())
+    }
+
+    /// Whether `token` is past its delisting notice, so holdings can only
+    /// leave through its redemptions.
+    fn redeemable(env: &Env, token: &Address) -> bool {
+        matches!(
+            PaperCurveClient::new(env, token).delisting_phase(),
+            DelistPhase::Redemption | DelistPhase::Closed
+        )
+    }
+
+    /// What selling `amount` of `token` back to its curve would pay, or
+    /// redeeming it once the token is delisted.
+    fn value_of(env: &Env, token: &Address, amount: i128) -> i128 {
+        if amount <= 0 {
+            return 0;
+        }
+        let curve = PaperCurveClient::new(env, token);
+        if Self::redeemable(env, token) {
+            return curve.quote_redeem(&amount);
+        }
+        curve.quote_sell(&amount).cost
+    }
+
+    fn cash(env: &Env, cfg: &BasketConfig) -> i128 {
+        token::Client::new(env, &cfg.payment_token).balance(&env.current_contract_address())
+    }
+
+    /// Liquidation value of the basket: every holding sold to its curve plus
+    /// the payment asset left over from earlier buys.
+    fn net_value(env: &Env, cfg: &BasketConfig, constituents: &Vec<Constituent>) -> i128 {
+        let mut total = Self::cash(env, cfg);
+        for c in constituents.iter() {
+            total += Self::value_of(env, &c.token, Self::holding(env, &c.token));
+        }
+        total
+    }
+
+    /// Buys as much of `token` as `budget` covers. Paper tokens pull the
+    /// payment with `transfer_from` on the buyer's own allowance, so the
+    /// basket approves itself and authorizes that nested call. Tokens being
+    /// delisted take no buys, so their budget stays as cash.
+    fn buy_with(env: &Env, cfg: &BasketConfig, token: &Address, budget: i128) {
+        let curve = PaperCurveClient::new(env, token);
+        if curve.delisting_phase() != DelistPhase::Listed {
+            return;
+        }
+        let amount = curve.quote_buy_for_payment(&budget);
+        if amount <= 0 {
+            return;
+        }
+        let cost = curve.quote_buy(&amount).cost;
+        let this = env.current_contract_address();
+        token::Client::new(env, &cfg.payment_token).approve(
+            &this,
+            &this,
+            &cost,
+            &env.ledger().sequence(),
+        );
+        env.authorize_as_current_contract(vec![
+            env,
+            InvokerContractAuthEntry::Contract(SubContractInvocation {
+                context: ContractContext {
+                    contract: cfg.payment_token.clone(),
+                    fn_name: Symbol::new(env, "transfer_from"),
+                    args: (this.clone(), this.clone(), token.clone(), cost).into_val(env),
+                },
+                sub_invocations: Vec::new(env),
+            }),
+        ]);
+        curve.buy(&this, &amount, &cost);
+    }
+
+    /// Sells `amount` of `token` to its curve, or redeems it once the token
+    /// is delisted.
+    fn sell(env: &Env, token: &Address, amount: i128) -> i128 {
+        if amount <= 0 {
+            return 0;
+        }
+        let curve = PaperCurveClient::new(env, token);
+        let this = env.current_contract_address();
+        if Self::redeemable(env, token) {
+            return curve.redeem(&this, &amount);
+        }
+        curve.sell(&this, &amount, &0).cost
+    }
+}
+
+#[contractimpl]
+impl PapexBasket {
+    pub fn init(
+        env: Env,
+        curator: Address,
+        name: String,
+        symbol: String,
+        payment_token: Address,
+        marketplace: Address,
+        constituents: Vec<Constituent>,
+    ) {
+        if env.storage().instance().has(&k_cfg()) {
+            panic!("already initialized");
+        }
+        curator.require_auth();
+
+        let cfg = BasketConfig {
+            curator,
+            name,
+            symbol,
+            payment_token,
+            marketplace,
+        };
+        Self::validate_constituents(&env, &cfg, &constituents);
+        env.storage().instance().set(&k_cfg(), &cfg);
+        env.storage().instance().set(&k_constituents(), &constituents);
+        Self::write_total_shares(&env, 0);
+    }
+
+    pub fn config(env: Env) -> BasketConfig {
+        Self::read_cfg(&env)
+    }
+
+    pub fn constituents(env: Env) -> Vec<Constituent> {
+        Self::read_constituents(&env)
+    }
+
+    /// Paper tokens held per constituent, in constituent order.
+    pub fn holdings(env: Env) -> Vec<i128> {
+        let mut out = Vec::new(&env);
+        for c in Self::read_constituents(&env).iter() {
+            out.push_back(Self::holding(&env, &c.token));
+        }
+        out
+    }
+
+    /// Liquidation value of the whole basket in the payment asset.
+    pub fn nav(env: Env) -> i128 {
+        let cfg = Self::read_cfg(&env);
+        Self::net_value(&env, &cfg, &Self::read_constituents(&env))
+    }
+
+    pub fn total_supply(env: Env) -> i128 {
+        Self::read_total_shares(&env)
+    }
+
+    pub fn balance_of(env: Env, who: Address) -> i128 {
+        Self::read_balances(&env).get(who).unwrap_or(0)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        Self::ensure_amount(amount);
+        from.require_auth();
+
+        let mut balances = Self::read_balances(&env);
+        let from_balance = balances.get(from.clone()).unwrap_or(0);
+        if from_balance < amount {
+            panic!("insufficient balance");
+        }
+        balances.set(from.clone(), from_balance - amount);
+        let to_balance = balances.get(to.clone()).unwrap_or(0);
+        balances.set(to.clone(), to_balance + amount);
+        Self::write_balances(&env, &balances);
+
+        env.events().publish((symbol_short!("transfer"),), (from, to, amount));
+    }
+
+    /// Spends `payment` on the constituents by weight and mints shares for the
+    /// value it added. The first deposit mints one share per unit of value;
+    /// later ones mint pro rata to the basket's value before the deposit, so
+    /// curve slippage is borne by the depositor.
+    pub fn deposit(env: Env, user: Address, payment: i128, min_shares: i128) -> i128 {
+        Self::ensure_amount(payment);
+        user.require_auth();
+        let cfg = Self::read_cfg(&env);
+        let constituents = Self::read_constituents(&env);
+        let nav_before = Self::net_value(&env, &cfg, &constituents);
+
+        token::Client::new(&env, &cfg.payment_token).transfer(
+            &user,
+            &env.current_contract_address(),
+            &payment,
+        );
+        for c in constituents.iter() {
+            let budget = payment * c.weight_bps as i128 / TOTAL_WEIGHT_BPS as i128;
+            Self::buy_with(&env, &cfg, &c.token, budget);
+        }
+        let added = Self::net_value(&env, &cfg, &constituents) - nav_before;
+
+        let total = Self::read_total_shares(&env);
+        let shares = if total == 0 || nav_before <= 0 {
+            added
+        } else {
+            total * added / nav_before
+        };
+        if shares <= 0 || shares < min_shares {
+            panic!("slippage");
+        }
+
+        let mut balances = Self::read_balances(&env);
+        let balance = balances.get(user.clone()).unwrap_or(0);
+        balances.set(user.clone(), balance + shares);
+        Self::write_balances(&env, &balances);
+        Self::write_total_shares(&env, total + shares);
+
+        env.events()
+            .publish((symbol_short!("deposit"),), (user, payment, shares));
+
+        shares
+    }
+
+    /// Burns `shares`, sells the same fraction of every holding and pays out
+    /// the proceeds together with that fraction of the idle payment asset.
+    /// The payout is capped at the shares' part of the basket's value before
+    /// the sale. Selling a slice from the top of a curve fetches more than
+    /// the average price the basket is valued at, and that surplus stays in
+    /// the basket for the remaining holders.
+    pub fn redeem(env: Env, user: Address, shares: i128, min_payment: i128) -> i128 {
+        Self::ensure_amount(shares);
+        user.require_auth();
+        let cfg = Self::read_cfg(&env);
+
+        let mut balances = Self::read_balances(&env);
+        let balance = balances.get(user.clone()).unwrap_or(0);
+        if balance < shares {
+            panic!("insufficient balance");
+        }
+        let total = Self::read_total_shares(&env);
+        let constituents = Self::read_constituents(&env);
+        let fair = Self::net_value(&env, &cfg, &constituents) * shares / total;
+
+        let mut payout = Self::cash(&env, &cfg) * shares / total;
+        for c in constituents.iter() {
+            let amount = Self::holding(&env, &c.token) * shares / total;
+            payout += Self::sell(&env, &c.token, amount);
+        }
+        let payout = payout.min(fair);
+        if payout < min_payment {
+            panic!("slippage");
+        }
+
+        balances.set(user.clone(), balance - shares);
+        Self::write_balances(&env, &balances);
+        Self::write_total_shares(&env, total - shares);
+        token::Client::new(&env, &cfg.payment_token).transfer(
+            &env.current_contract_address(),
+            &user,
+            &payout,
+        );
+
+        env.events()
+            .publish((symbol_short!("redeem"),), (user, shares, payout));
+
+        payout
+    }
+
+    /// Replaces the constituent set and trades towards the new weights:
+    /// dropped and overweight holdings are sold first, then the proceeds buy
+    /// the underweight ones. Targets are taken from the value before trading,
+    /// so curve slippage leaves the result close to, not exactly at, the
+    /// weights. Reverts if the basket ends up worth less than `min_nav`.
+    pub fn rebalance(env: Env, curator: Address, constituents: Vec<Constituent>, min_nav: i128) {
+        let cfg = Self::require_curator(&env, &curator);
+        Self::validate_constituents(&env, &cfg, &constituents);
+        let previous = Self::read_constituents(&env);
+        let nav = Self::net_value(&env, &cfg, &previous);
+        let target = |token: &Address| -> i128 {
+            let weight = constituents
+                .iter()
+                .find(|c| c.token == *token)
+                .map(|c| c.weight_bps)
+                .unwrap_or(0);
+            nav * weight as i128 / TOTAL_WEIGHT_BPS as i128
+        };
+
+        for c in previous.iter() {
+            let held = Self::holding(&env, &c.token);
+            let value = Self::value_of(&env, &c.token, held);
+            let goal = target(&c.token);
+            if value > goal {
+                Self::sell(&env, &c.token, held - held * goal / value);
+            }
+        }
+        for c in constituents.iter() {
+            let value = Self::value_of(&env, &c.token, Self::holding(&env, &c.token));
+            let budget = (target(&c.token) - value).min(Self::cash(&env, &cfg));
+            if budget > 0 {
+                Self::buy_with(&env, &cfg, &c.token, budget);
+            }
+        }
+        if Self::net_value(&env, &cfg, &constituents) < min_nav {
+            panic!("slippage");
+        }
+        env.storage().instance().set(&k_constituents(), &constituents);
+
+        env.events()
+            .publish((symbol_short!("rebal"),), (curator, constituents));
+    }
+
+    pub fn set_curator(env: Env, caller: Address, curator: Address) {
+        Self::require_curator(&env, &caller);
+        let mut cfg = Self::read_cfg(&env);
+        cfg.curator = curator.clone();
+        env.storage().instance().set(&k_cfg(), &cfg);
+
+        env.events()
+            .publish((symbol_short!("curator"),), (caller, curator));
+    }
+}
+
+#[cfg(test)]
+mod test;
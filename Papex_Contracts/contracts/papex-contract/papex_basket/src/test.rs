@@ -0,0 +1,306 @@
+#![cfg(test)]
+
+use super::*;
+use papex_papertoken::{PapexToken, PapexTokenClient};
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{token, vec, Address, Env, String};
+
+/// Fixed-price stand-in for a paper token's bonding curve. Buys pull the
+/// payment with `transfer_from` on the buyer's own allowance, like the real
+/// token does.
+#[contract]
+struct MockCurve;
+
+#[contractimpl]
+impl MockCurve {
+    pub fn setup(env: Env, payment: Address, price: i128) {
+        env.storage().instance().set(&symbol_short!("pay"), &payment);
+        env.storage().instance().set(&symbol_short!("price"), &price);
+    }
+
+    pub fn balance_of(env: Env, who: Address) -> i128 {
+        env.storage().instance().get(&who).unwrap_or(0)
+    }
+
+    pub fn payment_token(env: Env) -> Option<Address> {
+        env.storage().instance().get(&symbol_short!("pay"))
+    }
+
+    pub fn quote_buy(env: Env, amount: i128) -> CurveQuote {
+        let price: i128 = env.storage().instance().get(&symbol_short!("price")).unwrap();
+        CurveQuote { cost: price * amount, price_before: price, price_after: price }
+    }
+
+    pub fn quote_sell(env: Env, amount: i128) -> CurveQuote {
+        Self::quote_buy(env, amount)
+    }
+
+    pub fn quote_buy_for_payment(env: Env, payment: i128) -> i128 {
+        let price: i128 = env.storage().instance().get(&symbol_short!("price")).unwrap();
+        payment / price
+    }
+
+    pub fn buy(env: Env, buyer: Address, amount: i128, max_payment: i128) -> CurveQuote {
+        buyer.require_auth();
+        let quote = Self::quote_buy(env.clone(), amount);
+        assert!(quote.cost <= max_payment);
+        let payment = Self::payment_token(env.clone()).unwrap();
+        token::Client::new(&env, &payment).transfer_from(
+            &buyer,
+            &buyer,
+            &env.current_contract_address(),
+            &quote.cost,
+        );
+        let balance = Self::balance_of(env.clone(), buyer.clone());
+        env.storage().instance().set(&buyer, &(balance + amount));
+        quote
+    }
+
+    pub fn delisting_phase() -> DelistPhase {
+        DelistPhase::Listed
+    }
+
+    pub fn sell(env: Env, seller: Address, amount: i128, min_payment: i128) -> CurveQuote {
+        seller.require_auth();
+        let quote = Self::quote_sell(env.clone(), amount);
+        assert!(quote.cost >= min_payment);
+        let balance = Self::balance_of(env.clone(), seller.clone());
+        assert!(balance >= amount);
+        env.storage().instance().set(&seller, &(balance - amount));
+        let payment = Self::payment_token(env.clone()).unwrap();
+        token::Client::new(&env, &payment).transfer(
+            &env.current_contract_address(),
+            &seller,
+            &quote.cost,
+        );
+        quote
+    }
+}
+
+/// Marketplace stand-in that only answers listing lookups.
+#[contract]
+struct MockMarketplace;
+
+#[contractimpl]
+impl MockMarketplace {
+    pub fn list(env: Env, paper_id: u32, token: Address, is_active: bool) {
+        let owner = env.current_contract_address();
+        let data = Listing {
+            paper_id,
+            token,
+            metadata_uri: String::from_str(&env, "ipfs://p"),
+            owner,
+            is_active,
+            created_at: 0,
+        };
+        env.storage().instance().set(&paper_id, &ListingRecord { id: paper_id, data });
+    }
+
+    pub fn get_listing(env: Env, paper_id: u32) -> Option<ListingRecord> {
+        env.storage().instance().get(&paper_id)
+    }
+}
+
+fn curve(env: &Env, payment: &Address, price: i128) -> Address {
+    let id = env.register_contract(None, MockCurve);
+    MockCurveClient::new(env, &id).setup(payment, &price);
+    id
+}
+
+fn weights(env: &Env, a: &Address, a_bps: u32, b: &Address, b_bps: u32) -> Vec<Constituent> {
+    vec![
+        env,
+        Constituent { paper_id: 1, token: a.clone(), weight_bps: a_bps },
+        Constituent { paper_id: 2, token: b.clone(), weight_bps: b_bps },
+    ]
+}
+
+#[test]
+fn deposits_redemptions_and_rebalancing_track_weights() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let curator = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(curator.clone()).address();
+    let other = env.register_stellar_asset_contract_v2(curator.clone()).address();
+    let asset = token::StellarAssetClient::new(&env, &payment);
+    asset.mint(&alice, &1_000);
+    asset.mint(&bob, &505);
+    let paper_a = curve(&env, &payment, 10);
+    let paper_b = curve(&env, &payment, 4);
+    let foreign = curve(&env, &other, 4);
+    let unlisted = curve(&env, &payment, 4);
+    let market = env.register_contract(None, MockMarketplace);
+    let listings = MockMarketplaceClient::new(&env, &market);
+    listings.list(&1, &paper_a, &true);
+    listings.list(&2, &paper_b, &true);
+
+    let contract_id = env.register_contract(None, PapexBasket);
+    let client = PapexBasketClient::new(&env, &contract_id);
+    let name = String::from_str(&env, "CRISPR");
+    let symbol = String::from_str(&env, "CRSP");
+    let bad_weights = weights(&env, &paper_a, 6_000, &paper_b, 3_000);
+    assert!(client.try_init(&curator, &name, &symbol, &payment, &market, &bad_weights).is_err());
+    let bad_token = weights(&env, &paper_a, 6_000, &unlisted, 4_000);
+    assert!(client.try_init(&curator, &name, &symbol, &payment, &market, &bad_token).is_err());
+    listings.list(&2, &foreign, &true);
+    let bad_asset = weights(&env, &paper_a, 6_000, &foreign, 4_000);
+    assert!(client.try_init(&curator, &name, &symbol, &payment, &market, &bad_asset).is_err());
+    listings.list(&2, &paper_b, &false);
+    let initial = weights(&env, &paper_a, 6_000, &paper_b, 4_000);
+    assert!(client.try_init(&curator, &name, &symbol, &payment, &market, &initial).is_err());
+    listings.list(&2, &paper_b, &true);
+    client.init(&curator, &name, &symbol, &payment, &market, &initial);
+
+    // 600 buys 60 A and 400 buys 100 B.
+    assert_eq!(client.deposit(&alice, &1_000, &1_000), 1_000);
+    assert_eq!(client.holdings(), vec![&env, 60i128, 100]);
+    assert_eq!(client.nav(), 1_000);
+
+    // 303 buys 30 A and 202 buys 50 B; the 5 left over stays as cash.
+    assert!(client.try_deposit(&bob, &505, &506).is_err());
+    assert_eq!(client.deposit(&bob, &505, &505), 505);
+    assert_eq!(client.holdings(), vec![&env, 90i128, 150]);
+    assert_eq!(client.nav(), 1_505);
+    assert_eq!(client.total_supply(), 1_505);
+
+    // Targets of 301 A and 1_204 B: sell 60 A, then 604 buys 151 B.
+    let tilted = weights(&env, &paper_a, 2_000, &paper_b, 8_000);
+    assert!(client.try_rebalance(&alice, &tilted, &0).is_err());
+    assert!(client.try_rebalance(&curator, &tilted, &1_506).is_err());
+    client.rebalance(&curator, &tilted, &1_505);
+    assert_eq!(client.constituents(), tilted);
+    assert_eq!(client.holdings(), vec![&env, 30i128, 301]);
+    assert_eq!(client.nav(), 1_505);
+
+    // Bob's 505 of 1_505 shares sell 10 A and 101 B.
+    assert!(client.try_redeem(&bob, &506, &0).is_err());
+    assert!(client.try_redeem(&bob, &505, &505).is_err());
+    assert_eq!(client.redeem(&bob, &505, &504), 504);
+    assert_eq!(token::Client::new(&env, &payment).balance(&bob), 504);
+    assert_eq!(client.balance_of(&bob), 0);
+    assert_eq!(client.holdings(), vec![&env, 20i128, 200]);
+
+    client.transfer(&alice, &bob, &400);
+    assert_eq!(client.balance_of(&alice), 600);
+    assert_eq!(client.total_supply(), 1_000);
+}
+
+#[test]
+fn redemptions_on_a_sloped_curve_cannot_drain_other_holders() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let curator = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(curator.clone()).address();
+    let usd = token::Client::new(&env, &payment);
+    let asset = token::StellarAssetClient::new(&env, &payment);
+    asset.mint(&alice, &5_000);
+    asset.mint(&bob, &15_000);
+
+    let paper = env.register_contract(None, PapexToken);
+    let paper_client = PapexTokenClient::new(&env, &paper);
+    paper_client.init(
+        &curator,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000_000,
+        &1,
+        &1,
+        &Option::Some(payment.clone()),
+        &0,
+        &0,
+    );
+    paper_client.set_trading(&curator, &true);
+    let market = env.register_contract(None, MockMarketplace);
+    MockMarketplaceClient::new(&env, &market).list(&1, &paper, &true);
+
+    let contract_id = env.register_contract(None, PapexBasket);
+    let client = PapexBasketClient::new(&env, &contract_id);
+    let all_in = vec![&env, Constituent { paper_id: 1, token: paper.clone(), weight_bps: 10_000 }];
+    let name = String::from_str(&env, "Single");
+    let symbol = String::from_str(&env, "ONE");
+    client.init(&curator, &name, &symbol, &payment, &market, &all_in);
+
+    // 5_000 buys 99 tokens and 15_000 the next 100, both valued at the
+    // average price of the whole holding.
+    assert_eq!(client.deposit(&alice, &5_000, &0), 5_000);
+    assert_eq!(client.deposit(&bob, &15_000, &0), 14_950);
+    assert_eq!(client.nav(), 19_950);
+
+    // Selling Bob's half from the top of the curve fetches 18_662; he gets
+    // his 14_950 share of the value and the rest stays with Alice.
+    assert_eq!(client.redeem(&bob, &14_950, &0), 14_950);
+    assert_eq!(usd.balance(&bob), 14_950);
+    assert_eq!(client.nav(), 5_025);
+    assert_eq!(client.redeem(&alice, &5_000, &0), 5_025);
+    assert_eq!(client.total_supply(), 0);
+}
+
+#[test]
+fn delisted_constituents_are_redeemed_and_skipped_by_deposits() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let curator = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(curator.clone()).address();
+    let usd = token::Client::new(&env, &payment);
+    let asset = token::StellarAssetClient::new(&env, &payment);
+    asset.mint(&alice, &5_000);
+    asset.mint(&bob, &1_000);
+
+    let paper = env.register_contract(None, PapexToken);
+    let paper_client = PapexTokenClient::new(&env, &paper);
+    paper_client.init(
+        &curator,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000_000,
+        &1,
+        &1,
+        &Option::Some(payment.clone()),
+        &0,
+        &0,
+    );
+    paper_client.set_trading(&curator, &true);
+    paper_client.set_delister(&curator, &Option::Some(curator.clone()));
+    let market = env.register_contract(None, MockMarketplace);
+    MockMarketplaceClient::new(&env, &market).list(&1, &paper, &true);
+
+    let contract_id = env.register_contract(None, PapexBasket);
+    let client = PapexBasketClient::new(&env, &contract_id);
+    let all_in = vec![&env, Constituent { paper_id: 1, token: paper.clone(), weight_bps: 10_000 }];
+    let name = String::from_str(&env, "Single");
+    let symbol = String::from_str(&env, "ONE");
+    client.init(&curator, &name, &symbol, &payment, &market, &all_in);
+    client.deposit(&alice, &5_000, &0);
+
+    let week = 7 * 86_400;
+    paper_client.start_delisting(&curator, &week, &(week + 30 * 86_400));
+
+    // The paper takes no buys during the notice, so Bob's deposit stays as
+    // cash instead of reverting.
+    assert_eq!(client.deposit(&bob, &1_000, &0), 1_000);
+    assert_eq!(client.holdings(), vec![&env, 99i128]);
+    assert_eq!(client.nav(), 6_000);
+
+    // Past the notice the paper can no longer be sold; the basket values and
+    // pays out its holding through the token's redemptions instead.
+    env.ledger().with_mut(|l| l.timestamp = week);
+    assert_eq!(client.nav(), 6_000);
+    assert_eq!(client.redeem(&bob, &1_000, &0), 975);
+    assert_eq!(client.redeem(&alice, &5_000, &0), 5_025);
+    assert_eq!(usd.balance(&alice), 5_025);
+    assert_eq!(usd.balance(&bob), 975);
+    assert_eq!(paper_client.balance_of(&contract_id), 0);
+    assert_eq!(client.total_supply(), 0);
+}
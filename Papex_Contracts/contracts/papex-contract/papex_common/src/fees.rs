@@ -0,0 +1,74 @@
+use core::option::Option;
+use soroban_sdk::{contracttype, symbol_short, token, Address, Env, Map, Symbol};
+
+fn k_fee() -> Symbol { symbol_short!("fee") }
+fn k_fee_waivers() -> Symbol { symbol_short!("fwv") }
+
+/// Flat fee charged in `asset` and sent to `treasury`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct FeeConfig {
+    pub asset: Address,
+    pub amount: i128,
+    pub treasury: Address,
+}
+
+fn waivers(env: &Env) -> Map<Address, bool> {
+    env.storage()
+        .instance()
+        .get(&k_fee_waivers())
+        .unwrap_or(Map::new(env))
+}
+
+pub fn config(env: &Env) -> Option<FeeConfig> {
+    env.storage().instance().get(&k_fee())
+}
+
+pub fn set_config(env: &Env, fee: Option<FeeConfig>) {
+    match fee {
+        Option::Some(fee) => {
+            if fee.amount < 0 {
+                panic!("invalid fee");
+            }
+            env.events()
+                .publish((symbol_short!("feeset"),), (fee.asset.clone(), fee.amount));
+            env.storage().instance().set(&k_fee(), &fee);
+        }
+        Option::None => {
+            env.storage().instance().remove(&k_fee());
+            env.events().publish((symbol_short!("feeset"),), ());
+        }
+    }
+}
+
+pub fn is_waived(env: &Env, who: &Address) -> bool {
+    waivers(env).get(who.clone()).unwrap_or(false)
+}
+
+pub fn set_waiver(env: &Env, who: &Address, waived: bool) {
+    let mut waivers = waivers(env);
+    if waived {
+        waivers.set(who.clone(), true);
+    } else {
+        waivers.remove(who.clone());
+    }
+    env.storage().instance().set(&k_fee_waivers(), &waivers);
+    env.events()
+        .publish((symbol_short!("feewaive"),), (who.clone(), waived));
+}
+
+/// Charges the configured fee to `payer` unless none is set or `payer` is
+/// waived.
+pub fn charge(env: &Env, payer: &Address) {
+    let fee = match config(env) {
+        Option::Some(fee) if fee.amount > 0 => fee,
+        _ => return,
+    };
+    if is_waived(env, payer) {
+        return;
+    }
+
+    token::Client::new(env, &fee.asset).transfer(payer, &fee.treasury, &fee.amount);
+    env.events()
+        .publish((symbol_short!("fee"),), (payer.clone(), fee.asset, fee.amount));
+}
@@ -0,0 +1,10 @@
+#![no_std]
+
+//! Helpers shared by the Papex contracts. State lives in the calling
+//! contract's instance storage, under the keys each contract already used.
+
+pub mod fees;
+pub mod pause;
+
+pub use fees::FeeConfig;
+pub use pause::{PauseController, PauseControllerClient};
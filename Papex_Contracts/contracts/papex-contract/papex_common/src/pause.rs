@@ -0,0 +1,53 @@
+use core::option::Option;
+use soroban_sdk::{contractclient, symbol_short, Address, Env, Symbol};
+
+fn k_paused() -> Symbol { symbol_short!("paused") }
+fn k_pauser() -> Symbol { symbol_short!("pauser") }
+fn k_pause_ctl() -> Symbol { symbol_short!("pctl") }
+
+/// Any contract exposing `is_paused` can act as a shared pause switch.
+#[contractclient(name = "PauseControllerClient")]
+pub trait PauseController {
+    fn is_paused(env: Env) -> bool;
+}
+
+/// True when the contract's own flag is set or its controller is paused.
+pub fn paused(env: &Env) -> bool {
+    if env.storage().instance().get(&k_paused()).unwrap_or(false) {
+        return true;
+    }
+    match controller(env) {
+        Option::Some(ctl) => PauseControllerClient::new(env, &ctl).is_paused(),
+        Option::None => false,
+    }
+}
+
+pub fn set_paused(env: &Env, paused: bool) {
+    env.storage().instance().set(&k_paused(), &paused);
+}
+
+pub fn pauser(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&k_pauser())
+}
+
+pub fn set_pauser(env: &Env, caller: &Address, pauser: Option<Address>) {
+    match &pauser {
+        Option::Some(pauser) => env.storage().instance().set(&k_pauser(), pauser),
+        Option::None => env.storage().instance().remove(&k_pauser()),
+    }
+    env.events()
+        .publish((symbol_short!("pauser"),), (caller.clone(), pauser));
+}
+
+pub fn controller(env: &Env) -> Option<Address> {
+    env.storage().instance().get(&k_pause_ctl())
+}
+
+pub fn set_controller(env: &Env, caller: &Address, controller: Option<Address>) {
+    match &controller {
+        Option::Some(ctl) => env.storage().instance().set(&k_pause_ctl(), ctl),
+        Option::None => env.storage().instance().remove(&k_pause_ctl()),
+    }
+    env.events()
+        .publish((symbol_short!("pausectl"),), (caller.clone(), controller));
+}
@@ -0,0 +1,218 @@
+use soroban_sdk::{
+    contractimpl, contracttype, symbol_short, Address, Env, IntoVal, Map, Symbol, Val, Vec,
+};
+
+use crate::{history, CandleInterval, DataKey, PapexMarketplace, PapexMarketplaceClient, Trade};
+
+fn k_top_listings() -> Symbol { symbol_short!("toplst") }
+fn k_top_traders() -> Symbol { symbol_short!("toptrd") }
+
+/// Entries kept on each leaderboard. Volumes only grow, so a bounded board
+/// stays exact: an entry can only climb onto it when it trades. Listings and
+/// traders are ranked per quote asset, since amounts in different assets do
+/// not add up.
+const LEADERBOARD_SIZE: u32 = 20;
+
+/// Running totals for one listing in one quote asset.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[contracttype]
+pub struct ListingTotals {
+    pub volume: i128,
+    pub trades: u64,
+    pub traders: u32,
+}
+
+/// Figures cover trades quoted in `asset`; volumes are the trades' `cost`.
+/// The 24h and 7d figures sum hourly candles, so they move in whole hours.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ListingStats {
+    pub paper_id: u32,
+    pub asset: Address,
+    pub volume_24h: i128,
+    pub volume_7d: i128,
+    pub volume_all_time: i128,
+    pub trade_count: u64,
+    pub distinct_traders: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ListingVolume {
+    pub paper_id: u32,
+    pub volume: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TraderVolume {
+    pub trader: Address,
+    pub volume: i128,
+}
+
+fn listing_totals(env: &Env, paper_id: u32, asset: &Address) -> ListingTotals {
+    env.storage()
+        .persistent()
+        .get(&DataKey::ListingTotals(paper_id, asset.clone()))
+        .unwrap_or_default()
+}
+
+fn top_listings(env: &Env) -> Map<Address, Vec<ListingVolume>> {
+    env.storage()
+        .instance()
+        .get(&k_top_listings())
+        .unwrap_or(Map::new(env))
+}
+
+fn trader_volume(env: &Env, trader: &Address, asset: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::TraderVolume(trader.clone(), asset.clone()))
+        .unwrap_or(0)
+}
+
+fn top_traders(env: &Env) -> Map<Address, Vec<TraderVolume>> {
+    env.storage()
+        .instance()
+        .get(&k_top_traders())
+        .unwrap_or(Map::new(env))
+}
+
+fn write_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    env.storage().persistent().set(key, value);
+    history::bump(env, key);
+}
+
+/// Index of the first entry whose volume is below `volume`, i.e. where an
+/// entry with `volume` belongs on a board sorted from largest to smallest.
+fn rank(volumes: impl Iterator<Item = i128>, volume: i128) -> u32 {
+    let mut index = 0;
+    for v in volumes {
+        if v < volume {
+            break;
+        }
+        index += 1;
+    }
+    index
+}
+
+fn update_top_listings(env: &Env, asset: &Address, paper_id: u32, volume: i128) {
+    let mut boards = top_listings(env);
+    let mut board = boards.get(asset.clone()).unwrap_or(Vec::new(env));
+    if let Option::Some(i) = board.iter().position(|e| e.paper_id == paper_id) {
+        board.remove(i as u32);
+    }
+    let at = rank(board.iter().map(|e| e.volume), volume);
+    if at < LEADERBOARD_SIZE {
+        board.insert(at, ListingVolume { paper_id, volume });
+        if board.len() > LEADERBOARD_SIZE {
+            board.pop_back();
+        }
+    }
+    boards.set(asset.clone(), board);
+    env.storage().instance().set(&k_top_listings(), &boards);
+}
+
+fn update_top_traders(env: &Env, asset: &Address, trader: &Address, volume: i128) {
+    let mut boards = top_traders(env);
+    let mut board = boards.get(asset.clone()).unwrap_or(Vec::new(env));
+    if let Option::Some(i) = board.iter().position(|e| e.trader == *trader) {
+        board.remove(i as u32);
+    }
+    let at = rank(board.iter().map(|e| e.volume), volume);
+    if at < LEADERBOARD_SIZE {
+        board.insert(at, TraderVolume { trader: trader.clone(), volume });
+        if board.len() > LEADERBOARD_SIZE {
+            board.pop_back();
+        }
+    }
+    boards.set(asset.clone(), board);
+    env.storage().instance().set(&k_top_traders(), &boards);
+}
+
+/// Counts the trade once for the listing in `asset` and adds its cost to
+/// the volume of `trade.trader` and, when known, of `counterparty`.
+pub(crate) fn record(
+    env: &Env,
+    paper_id: u32,
+    trade: &Trade,
+    asset: &Address,
+    counterparty: Option<&Address>,
+) {
+    let mut totals = listing_totals(env, paper_id, asset);
+    totals.volume += trade.cost;
+    totals.trades += 1;
+
+    let mut parties = Vec::from_array(env, [trade.trader.clone()]);
+    if let Option::Some(other) = counterparty {
+        if *other != trade.trader {
+            parties.push_back(other.clone());
+        }
+    }
+    for party in parties.iter() {
+        let seen = DataKey::TraderSeen(paper_id, asset.clone(), party.clone());
+        if !env.storage().persistent().has(&seen) {
+            totals.traders += 1;
+            write_persistent(env, &seen, &true);
+        }
+
+        let volume = trader_volume(env, &party, asset) + trade.cost;
+        write_persistent(env, &DataKey::TraderVolume(party.clone(), asset.clone()), &volume);
+        update_top_traders(env, asset, &party, volume);
+    }
+    write_persistent(env, &DataKey::ListingTotals(paper_id, asset.clone()), &totals);
+    update_top_listings(env, asset, paper_id, totals.volume);
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    pub fn listing_stats(env: Env, paper_id: u32, asset: Address) -> ListingStats {
+        let totals = listing_totals(&env, paper_id, &asset);
+        let now = env.ledger().timestamp();
+        let (mut volume_24h, mut volume_7d) = (0, 0);
+        // Candles starting in the hour that contains `now - window` count in full.
+        let since = |window: u64| now.saturating_sub(window) / 3_600 * 3_600;
+        let hours = Self::candles(
+            env.clone(),
+            paper_id,
+            asset.clone(),
+            CandleInterval::Hour,
+            since(604_800),
+            168,
+        );
+        for candle in hours.iter() {
+            volume_7d += candle.quote_volume;
+            if candle.start >= since(86_400) {
+                volume_24h += candle.quote_volume;
+            }
+        }
+        ListingStats {
+            paper_id,
+            asset,
+            volume_24h,
+            volume_7d,
+            volume_all_time: totals.volume,
+            trade_count: totals.trades,
+            distinct_traders: totals.traders,
+        }
+    }
+
+    /// Up to `n` listings with the largest all-time volume in `asset`,
+    /// largest first.
+    pub fn top_listings(env: Env, asset: Address, n: u32) -> Vec<ListingVolume> {
+        let board = top_listings(&env).get(asset).unwrap_or(Vec::new(&env));
+        board.slice(..n.min(board.len()))
+    }
+
+    /// Up to `n` traders with the largest all-time volume in `asset`,
+    /// largest first.
+    pub fn top_traders(env: Env, asset: Address, n: u32) -> Vec<TraderVolume> {
+        let board = top_traders(&env).get(asset).unwrap_or(Vec::new(&env));
+        board.slice(..n.min(board.len()))
+    }
+
+    /// All-time volume of `trader` in `asset`, as buyer or seller.
+    pub fn trader_volume(env: Env, trader: Address, asset: Address) -> i128 {
+        trader_volume(&env, &trader, &asset)
+    }
+}
@@ -0,0 +1,306 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, token, Address, Env, Symbol};
+
+use crate::{
+    delisting, history, refunds, DataKey, PapexMarketplace, PapexMarketplaceClient, Trade,
+};
+
+fn k_next_auction() -> Symbol { symbol_short!("nauc") }
+
+/// Prices are for the whole lot. English auctions take ascending bids from
+/// `reserve` in steps of at least `min_increment`; Dutch auctions fall
+/// linearly from `start_price` to `floor_price` over the auction.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum AuctionKind {
+    English(i128, i128),
+    Dutch(i128, i128),
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Auction {
+    pub id: u32,
+    pub paper_id: u32,
+    pub seller: Address,
+    pub token: Address,
+    pub asset: Address,
+    pub amount: i128,
+    pub kind: AuctionKind,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub highest_bidder: Option<Address>,
+    pub highest_bid: i128,
+    pub settled: bool,
+}
+
+fn read_auction(env: &Env, auction_id: u32) -> Auction {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Auction(auction_id))
+        .unwrap_or_else(|| panic!("auction not found"))
+}
+
+fn write_auction(env: &Env, auction: &Auction) {
+    let key = DataKey::Auction(auction.id);
+    env.storage().persistent().set(&key, auction);
+    history::bump(env, &key);
+}
+
+fn dutch_price(env: &Env, auction: &Auction, start: i128, floor: i128) -> i128 {
+    let now = env.ledger().timestamp().min(auction.ends_at);
+    let elapsed = (now - auction.starts_at) as i128;
+    let duration = (auction.ends_at - auction.starts_at) as i128;
+    start - (start - floor) * elapsed / duration
+}
+
+/// Credits the proceeds to the seller, who collects them with
+/// `withdraw_refund`, and records the sale in the listing's trade history.
+/// Callers hand over the lot.
+fn close_sale(env: &Env, auction: &mut Auction, winner: &Address, price: i128) {
+    refunds::credit(env, &auction.seller, &auction.asset, price);
+    auction.highest_bidder = Option::Some(winner.clone());
+    auction.highest_bid = price;
+    auction.settled = true;
+    write_auction(env, auction);
+
+    let trade = Trade {
+        trader: winner.clone(),
+        amount: auction.amount,
+        cost: price,
+        is_buy: true,
+        timestamp: env.ledger().timestamp(),
+    };
+    PapexMarketplace::append_trade(
+        env,
+        auction.paper_id,
+        &trade,
+        &auction.asset,
+        Option::Some(&auction.seller),
+    );
+    env.events()
+        .publish((symbol_short!("aucsold"),), (auction.id, winner.clone(), price));
+}
+
+fn open_auction(env: &Env, auction_id: u32) -> Auction {
+    let auction = read_auction(env, auction_id);
+    if auction.settled {
+        panic!("auction settled");
+    }
+    if env.ledger().timestamp() >= auction.ends_at {
+        panic!("auction ended");
+    }
+    auction
+}
+
+/// Bids and purchases buy into the listing, so they stop once it is
+/// deactivated or starts delisting. A bid already placed can still settle.
+fn ensure_biddable(env: &Env, auction: &Auction) {
+    let listing = PapexMarketplace::listings(env).get(auction.paper_id).unwrap();
+    if !listing.is_active {
+        panic!("listing inactive");
+    }
+    delisting::ensure_listed(env, auction.paper_id);
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// Escrows `amount` paper tokens from the listing owner and auctions them
+    /// for `asset` over `duration` seconds.
+    pub fn create_auction(
+        env: Env,
+        caller: Address,
+        paper_id: u32,
+        asset: Address,
+        amount: i128,
+        kind: AuctionKind,
+        duration: u64,
+    ) -> u32 {
+        caller.require_auth();
+        Self::ensure_not_paused(&env);
+        let listing = Self::listings(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not found"));
+        if caller != listing.owner {
+            panic!("only listing owner");
+        }
+        if !listing.is_active {
+            panic!("listing inactive");
+        }
+        delisting::ensure_listed(&env, paper_id);
+        let valid = match &kind {
+            AuctionKind::English(reserve, step) => *reserve > 0 && *step > 0,
+            AuctionKind::Dutch(start, floor) => *floor > 0 && start > floor,
+        };
+        if !valid || amount <= 0 || duration == 0 {
+            panic!("invalid auction");
+        }
+
+        token::Client::new(&env, &listing.token).transfer(
+            &caller,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        let auction_id: u32 = env.storage().instance().get(&k_next_auction()).unwrap_or(0);
+        env.storage()
+            .instance()
+            .set(&k_next_auction(), &auction_id.checked_add(1).expect("id overflow"));
+        let now = env.ledger().timestamp();
+        write_auction(
+            &env,
+            &Auction {
+                id: auction_id,
+                paper_id,
+                seller: caller.clone(),
+                token: listing.token,
+                asset,
+                amount,
+                kind,
+                starts_at: now,
+                ends_at: now + duration,
+                highest_bidder: Option::None,
+                highest_bid: 0,
+                settled: false,
+            },
+        );
+
+        env.events()
+            .publish((symbol_short!("auction"),), (auction_id, paper_id, caller, amount));
+
+        auction_id
+    }
+
+    pub fn get_auction(env: Env, auction_id: u32) -> Option<Auction> {
+        env.storage().persistent().get(&DataKey::Auction(auction_id))
+    }
+
+    /// Current Dutch price, or the lowest acceptable next bid for an English
+    /// auction.
+    pub fn auction_price(env: Env, auction_id: u32) -> i128 {
+        let auction = read_auction(&env, auction_id);
+        match auction.kind {
+            AuctionKind::English(reserve, step) => match auction.highest_bidder {
+                Option::Some(_) => auction.highest_bid + step,
+                Option::None => reserve,
+            },
+            AuctionKind::Dutch(start, floor) => dutch_price(&env, &auction, start, floor),
+        }
+    }
+
+    /// Bids on an English auction. The bid is escrowed and the previous
+    /// highest bid is credited to its bidder, who collects it with
+    /// `withdraw_refund`.
+    pub fn place_bid(env: Env, bidder: Address, auction_id: u32, bid: i128) -> Auction {
+        bidder.require_auth();
+        Self::ensure_not_paused(&env);
+        let mut auction = open_auction(&env, auction_id);
+        if !matches!(auction.kind, AuctionKind::English(_, _)) {
+            panic!("not an english auction");
+        }
+        ensure_biddable(&env, &auction);
+        if bid < Self::auction_price(env.clone(), auction_id) {
+            panic!("bid too low");
+        }
+
+        token::Client::new(&env, &auction.asset).transfer(
+            &bidder,
+            &env.current_contract_address(),
+            &bid,
+        );
+        if let Option::Some(previous) = auction.highest_bidder.clone() {
+            refunds::credit(&env, &previous, &auction.asset, auction.highest_bid);
+        }
+        auction.highest_bidder = Option::Some(bidder.clone());
+        auction.highest_bid = bid;
+        write_auction(&env, &auction);
+
+        env.events()
+            .publish((symbol_short!("aucbid"),), (auction_id, bidder, bid));
+
+        auction
+    }
+
+    /// Takes a Dutch auction's whole lot at the current price.
+    pub fn buy_auction(env: Env, buyer: Address, auction_id: u32, max_price: i128) -> Auction {
+        buyer.require_auth();
+        Self::ensure_not_paused(&env);
+        let mut auction = open_auction(&env, auction_id);
+        let price = match auction.kind {
+            AuctionKind::Dutch(start, floor) => dutch_price(&env, &auction, start, floor),
+            AuctionKind::English(_, _) => panic!("not a dutch auction"),
+        };
+        ensure_biddable(&env, &auction);
+        if price > max_price {
+            panic!("price above max");
+        }
+
+        token::Client::new(&env, &auction.asset).transfer(
+            &buyer,
+            &env.current_contract_address(),
+            &price,
+        );
+        token::Client::new(&env, &auction.token).transfer(
+            &env.current_contract_address(),
+            &buyer,
+            &auction.amount,
+        );
+        close_sale(&env, &mut auction, &buyer, price);
+        auction
+    }
+
+    /// Closes an auction after it ends: the lot is credited to the highest
+    /// English bidder and the price to the seller, both collected with
+    /// `withdraw_refund`, or the lot goes back to the seller if nothing sold.
+    /// Callable by anyone.
+    pub fn settle_auction(env: Env, auction_id: u32) -> Auction {
+        let mut auction = read_auction(&env, auction_id);
+        if auction.settled {
+            panic!("auction settled");
+        }
+        if env.ledger().timestamp() < auction.ends_at {
+            panic!("auction running");
+        }
+
+        match auction.highest_bidder.clone() {
+            Option::Some(winner) => {
+                let price = auction.highest_bid;
+                refunds::credit(&env, &winner, &auction.token, auction.amount);
+                close_sale(&env, &mut auction, &winner, price);
+            }
+            Option::None => {
+                token::Client::new(&env, &auction.token).transfer(
+                    &env.current_contract_address(),
+                    &auction.seller,
+                    &auction.amount,
+                );
+                auction.settled = true;
+                write_auction(&env, &auction);
+                env.events()
+                    .publish((symbol_short!("aucnone"),), (auction_id,));
+            }
+        }
+        auction
+    }
+
+    /// Withdraws an auction that has no bids yet and returns the lot.
+    pub fn cancel_auction(env: Env, caller: Address, auction_id: u32) {
+        caller.require_auth();
+        let mut auction = open_auction(&env, auction_id);
+        if caller != auction.seller {
+            panic!("only seller");
+        }
+        if auction.highest_bidder.is_some() {
+            panic!("auction has bids");
+        }
+        token::Client::new(&env, &auction.token).transfer(
+            &env.current_contract_address(),
+            &auction.seller,
+            &auction.amount,
+        );
+        auction.settled = true;
+        write_auction(&env, &auction);
+
+        env.events()
+            .publish((symbol_short!("auccncl"),), (auction_id,));
+    }
+}
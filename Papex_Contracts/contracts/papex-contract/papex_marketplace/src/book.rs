@@ -0,0 +1,379 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, token, Address, Env, Map, Symbol, Vec};
+
+use crate::{
+    delisting, history, refunds, DataKey, PapexMarketplace, PapexMarketplaceClient, Trade,
+};
+
+fn k_book_quotes() -> Symbol { symbol_short!("obq") }
+fn k_book_minimums() -> Symbol { symbol_short!("obmin") }
+fn k_next_book_order() -> Symbol { symbol_short!("nbko") }
+
+/// Upper bound on resting orders per listing. Expired orders are pruned
+/// before orders are counted.
+const MAX_BOOK_ORDERS: u32 = 50;
+/// Orders one owner may keep in a listing's book.
+const MAX_BOOK_ORDERS_PER_OWNER: u32 = 5;
+
+/// Resting bid or ask in a listing's order book. `price` is in the book's
+/// quote asset per paper token unit. Bids escrow `price * remaining` of the
+/// quote asset and asks escrow `remaining` paper tokens. `expires_at == 0`
+/// means the order does not expire.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct BookOrder {
+    pub id: u32,
+    pub paper_id: u32,
+    pub owner: Address,
+    pub is_bid: bool,
+    pub price: i128,
+    pub amount: i128,
+    pub remaining: i128,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+fn book_quotes(env: &Env) -> Map<u32, Address> {
+    env.storage()
+        .instance()
+        .get(&k_book_quotes())
+        .unwrap_or(Map::new(env))
+}
+
+fn read_order(env: &Env, order_id: u32) -> Option<BookOrder> {
+    env.storage().persistent().get(&DataKey::BookOrder(order_id))
+}
+
+fn write_order(env: &Env, order: &BookOrder) {
+    let key = DataKey::BookOrder(order.id);
+    env.storage().persistent().set(&key, order);
+    history::bump(env, &key);
+}
+
+fn book_index(env: &Env, paper_id: u32) -> Vec<u32> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::BookIndex(paper_id))
+        .unwrap_or(Vec::new(env))
+}
+
+fn write_book_index(env: &Env, paper_id: u32, index: &Vec<u32>) {
+    let key = DataKey::BookIndex(paper_id);
+    env.storage().persistent().set(&key, index);
+    history::bump(env, &key);
+}
+
+fn remove_order(env: &Env, index: &mut Vec<u32>, order_id: u32) {
+    env.storage().persistent().remove(&DataKey::BookOrder(order_id));
+    if let Option::Some(i) = index.first_index_of(order_id) {
+        index.remove(i);
+    }
+}
+
+fn book_minimums(env: &Env) -> Map<u32, i128> {
+    env.storage()
+        .instance()
+        .get(&k_book_minimums())
+        .unwrap_or(Map::new(env))
+}
+
+fn expired(env: &Env, order: &BookOrder) -> bool {
+    order.expires_at != 0 && env.ledger().timestamp() >= order.expires_at
+}
+
+fn quote_asset(env: &Env, paper_id: u32) -> Address {
+    book_quotes(env)
+        .get(paper_id)
+        .unwrap_or_else(|| panic!("order book disabled"))
+}
+
+fn refund(env: &Env, order: &BookOrder) {
+    let this = env.current_contract_address();
+    if order.is_bid {
+        token::Client::new(env, &quote_asset(env, order.paper_id)).transfer(
+            &this,
+            &order.owner,
+            &(order.price * order.remaining),
+        );
+    } else {
+        let listing = PapexMarketplace::listings(env).get(order.paper_id).unwrap();
+        token::Client::new(env, &listing.token).transfer(&this, &order.owner, &order.remaining);
+    }
+}
+
+/// Credits an order's remaining escrow to its owner, who collects it with
+/// `withdraw_refund`.
+fn credit_escrow(env: &Env, order: &BookOrder, quote: &Address, paper: &Address) {
+    if order.is_bid {
+        refunds::credit(env, &order.owner, quote, order.price * order.remaining);
+    } else {
+        refunds::credit(env, &order.owner, paper, order.remaining);
+    }
+}
+
+/// Removes a listing's expired orders and credits their escrows.
+fn prune_expired(env: &Env, index: &mut Vec<u32>, quote: &Address, paper: &Address) {
+    for order_id in index.clone().iter() {
+        let order = read_order(env, order_id).unwrap();
+        if !expired(env, &order) {
+            continue;
+        }
+        credit_escrow(env, &order, quote, paper);
+        remove_order(env, index, order_id);
+        env.events()
+            .publish((symbol_short!("bexpire"),), (order.paper_id, order_id, order.remaining));
+    }
+}
+
+/// Cancels every resting order of a listing and credits the escrows to
+/// their owners, who collect them with `withdraw_refund`. Nothing is
+/// transferred here, so no single owner can hold up the delisting.
+pub(crate) fn close_book(env: &Env, paper_id: u32) {
+    let quote = match book_quotes(env).get(paper_id) {
+        Option::Some(quote) => quote,
+        Option::None => return,
+    };
+    let paper = PapexMarketplace::listings(env).get(paper_id).unwrap().token;
+    let mut index = book_index(env, paper_id);
+    for order_id in index.clone().iter() {
+        let order = read_order(env, order_id).unwrap();
+        credit_escrow(env, &order, &quote, &paper);
+        remove_order(env, &mut index, order_id);
+        env.events()
+            .publish((symbol_short!("bcancel"),), (paper_id, order_id, order.remaining));
+    }
+    env.storage().persistent().remove(&DataKey::BookIndex(paper_id));
+}
+
+/// Best order on the other side of `taker` that crosses its price: lowest
+/// ask for a bid, highest bid for an ask, oldest first on ties.
+fn best_match(env: &Env, index: &Vec<u32>, taker: &BookOrder) -> Option<BookOrder> {
+    let mut best: Option<BookOrder> = Option::None;
+    for order_id in index.iter() {
+        let order = read_order(env, order_id).unwrap();
+        if order.is_bid == taker.is_bid {
+            continue;
+        }
+        let crosses = if taker.is_bid {
+            order.price <= taker.price
+        } else {
+            order.price >= taker.price
+        };
+        let better = match &best {
+            Option::None => true,
+            Option::Some(b) if taker.is_bid => order.price < b.price,
+            Option::Some(b) => order.price > b.price,
+        };
+        if crosses && better {
+            best = Option::Some(order);
+        }
+    }
+    best
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// Opens a peer-to-peer order book for a listing, priced in `quote`.
+    /// Orders worth less than `min_notional` of the quote asset are rejected.
+    pub fn enable_order_book(
+        env: Env,
+        caller: Address,
+        paper_id: u32,
+        quote: Address,
+        min_notional: i128,
+    ) {
+        caller.require_auth();
+        if min_notional <= 0 {
+            panic!("invalid minimum");
+        }
+        let cfg = Self::read_cfg(&env);
+        let listing = Self::listings(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not found"));
+        if caller != cfg.admin && caller != listing.owner {
+            panic!("not authorized");
+        }
+        let mut quotes = book_quotes(&env);
+        if quotes.contains_key(paper_id) {
+            panic!("order book exists");
+        }
+        quotes.set(paper_id, quote.clone());
+        env.storage().instance().set(&k_book_quotes(), &quotes);
+        let mut minimums = book_minimums(&env);
+        minimums.set(paper_id, min_notional);
+        env.storage().instance().set(&k_book_minimums(), &minimums);
+
+        env.events()
+            .publish((symbol_short!("book"),), (paper_id, quote, min_notional));
+    }
+
+    pub fn order_book_quote(env: Env, paper_id: u32) -> Option<Address> {
+        book_quotes(&env).get(paper_id)
+    }
+
+    pub fn order_book_min_notional(env: Env, paper_id: u32) -> Option<i128> {
+        book_minimums(&env).get(paper_id)
+    }
+
+    /// Places a bid or ask and matches it against live resting orders at
+    /// their prices. Any remainder rests in the book with its escrow until
+    /// filled, cancelled or, if `expires_at` is set, expired. Makers are paid
+    /// through `withdraw_refund`, as are expired orders, which are pruned
+    /// here.
+    pub fn place_book_order(
+        env: Env,
+        owner: Address,
+        paper_id: u32,
+        is_bid: bool,
+        price: i128,
+        amount: i128,
+        expires_at: u64,
+    ) -> BookOrder {
+        if price <= 0 || amount <= 0 {
+            panic!("invalid order");
+        }
+        if expires_at != 0 && expires_at <= env.ledger().timestamp() {
+            panic!("invalid expiry");
+        }
+        owner.require_auth();
+        Self::ensure_not_paused(&env);
+        let listing = Self::listings(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not found"));
+        if !listing.is_active {
+            panic!("listing inactive");
+        }
+        delisting::ensure_listed(&env, paper_id);
+        let quote = token::Client::new(&env, &quote_asset(&env, paper_id));
+        if price * amount < book_minimums(&env).get(paper_id).unwrap_or(0) {
+            panic!("order too small");
+        }
+        // Paper tokens expose the same `transfer(from, to, amount)` as SEP-41.
+        let paper = token::Client::new(&env, &listing.token);
+        let this = env.current_contract_address();
+
+        if is_bid {
+            quote.transfer(&owner, &this, &(price * amount));
+        } else {
+            paper.transfer(&owner, &this, &amount);
+        }
+
+        let order_id: u32 = env
+            .storage()
+            .instance()
+            .get(&k_next_book_order())
+            .unwrap_or(0);
+        env.storage()
+            .instance()
+            .set(&k_next_book_order(), &order_id.checked_add(1).expect("id overflow"));
+        let mut taker = BookOrder {
+            id: order_id,
+            paper_id,
+            owner: owner.clone(),
+            is_bid,
+            price,
+            amount,
+            remaining: amount,
+            created_at: env.ledger().timestamp(),
+            expires_at,
+        };
+
+        let mut index = book_index(&env, paper_id);
+        prune_expired(&env, &mut index, &quote.address, &paper.address);
+        while taker.remaining > 0 {
+            let mut maker = match best_match(&env, &index, &taker) {
+                Option::Some(maker) => maker,
+                Option::None => break,
+            };
+            let fill = taker.remaining.min(maker.remaining);
+            let cost = fill * maker.price;
+            if is_bid {
+                paper.transfer(&this, &owner, &fill);
+                refunds::credit(&env, &maker.owner, &quote.address, cost);
+                if taker.price > maker.price {
+                    quote.transfer(&this, &owner, &(fill * (taker.price - maker.price)));
+                }
+            } else {
+                quote.transfer(&this, &owner, &cost);
+                refunds::credit(&env, &maker.owner, &paper.address, fill);
+            }
+
+            taker.remaining -= fill;
+            maker.remaining -= fill;
+            if maker.remaining == 0 {
+                remove_order(&env, &mut index, maker.id);
+            } else {
+                write_order(&env, &maker);
+            }
+
+            let trade = Trade {
+                trader: owner.clone(),
+                amount: fill,
+                cost,
+                is_buy: is_bid,
+                timestamp: env.ledger().timestamp(),
+            };
+            Self::append_trade(
+                &env,
+                paper_id,
+                &trade,
+                &quote.address,
+                Option::Some(&maker.owner),
+            );
+            env.events()
+                .publish((symbol_short!("fill"),), (paper_id, taker.id, maker.id, fill, cost));
+        }
+
+        if taker.remaining > 0 {
+            if index.len() >= MAX_BOOK_ORDERS {
+                panic!("order book full");
+            }
+            let owned = index
+                .iter()
+                .filter(|id| read_order(&env, *id).unwrap().owner == owner)
+                .count() as u32;
+            if owned >= MAX_BOOK_ORDERS_PER_OWNER {
+                panic!("too many orders");
+            }
+            write_order(&env, &taker);
+            index.push_back(taker.id);
+        }
+        write_book_index(&env, paper_id, &index);
+
+        env.events().publish(
+            (symbol_short!("border"),),
+            (paper_id, order_id, owner, is_bid, price, amount),
+        );
+
+        taker
+    }
+
+    /// Cancels a resting or expired order and returns its escrow to the owner.
+    pub fn cancel_book_order(env: Env, caller: Address, order_id: u32) {
+        caller.require_auth();
+        let order = read_order(&env, order_id).unwrap_or_else(|| panic!("order not found"));
+        if caller != order.owner {
+            panic!("only order owner");
+        }
+        let mut index = book_index(&env, order.paper_id);
+        remove_order(&env, &mut index, order_id);
+        write_book_index(&env, order.paper_id, &index);
+        refund(&env, &order);
+
+        env.events()
+            .publish((symbol_short!("bcancel"),), (order.paper_id, order_id, order.remaining));
+    }
+
+    pub fn get_book_order(env: Env, order_id: u32) -> Option<BookOrder> {
+        read_order(&env, order_id)
+    }
+
+    /// Resting orders for a listing, including expired ones not yet
+    /// pruned, oldest first.
+    pub fn order_book(env: Env, paper_id: u32) -> Vec<BookOrder> {
+        let mut out = Vec::new(&env);
+        for order_id in book_index(&env, paper_id).iter() {
+            out.push_back(read_order(&env, order_id).unwrap());
+        }
+        out
+    }
+}
@@ -0,0 +1,135 @@
+use soroban_sdk::{contractimpl, contracttype, Address, Env, Vec};
+
+use crate::{history, DataKey, PapexMarketplace, PapexMarketplaceClient, Trade};
+
+/// Candle prices are fixed point with seven decimals, so trades priced below
+/// one quote unit per token still register.
+pub const PRICE_SCALE: i128 = 10_000_000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum CandleInterval {
+    Hour,
+    Day,
+}
+
+impl CandleInterval {
+    fn seconds(self) -> u64 {
+        match self {
+            CandleInterval::Hour => 3_600,
+            CandleInterval::Day => 86_400,
+        }
+    }
+
+    /// Candles kept per paper: one week of hours, one quarter of days.
+    fn capacity(self) -> u32 {
+        match self {
+            CandleInterval::Hour => 168,
+            CandleInterval::Day => 90,
+        }
+    }
+}
+
+/// Trades in one quote asset of one interval starting at `start`. Prices are
+/// `cost * PRICE_SCALE / amount` per trade; `volume` sums token amounts and
+/// `quote_volume` sums costs.
+/// Intervals without trades have no candle.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Candle {
+    pub start: u64,
+    pub open: i128,
+    pub high: i128,
+    pub low: i128,
+    pub close: i128,
+    pub volume: i128,
+    pub quote_volume: i128,
+    pub trades: u32,
+}
+
+fn read_candles(
+    env: &Env,
+    paper_id: u32,
+    asset: &Address,
+    interval: CandleInterval,
+) -> Vec<Candle> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Candles(paper_id, asset.clone(), interval))
+        .unwrap_or(Vec::new(env))
+}
+
+fn record_interval(
+    env: &Env,
+    paper_id: u32,
+    asset: &Address,
+    interval: CandleInterval,
+    trade: &Trade,
+) {
+    let price = trade.cost * PRICE_SCALE / trade.amount;
+    let start = trade.timestamp - trade.timestamp % interval.seconds();
+    let mut ring = read_candles(env, paper_id, asset, interval);
+
+    match ring.last() {
+        Option::Some(mut candle) if candle.start >= start => {
+            candle.high = candle.high.max(price);
+            candle.low = candle.low.min(price);
+            candle.close = price;
+            candle.volume += trade.amount;
+            candle.quote_volume += trade.cost;
+            candle.trades += 1;
+            ring.set(ring.len() - 1, candle);
+        }
+        _ => {
+            ring.push_back(Candle {
+                start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: trade.amount,
+                quote_volume: trade.cost,
+                trades: 1,
+            });
+            if ring.len() > interval.capacity() {
+                ring.pop_front();
+            }
+        }
+    }
+
+    let key = DataKey::Candles(paper_id, asset.clone(), interval);
+    env.storage().persistent().set(&key, &ring);
+    history::bump(env, &key);
+}
+
+/// Prices in different quote assets do not compare, so each asset of a paper
+/// gets its own candles.
+pub(crate) fn record(env: &Env, paper_id: u32, trade: &Trade, asset: &Address) {
+    record_interval(env, paper_id, asset, CandleInterval::Hour, trade);
+    record_interval(env, paper_id, asset, CandleInterval::Day, trade);
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// Up to `limit` candles of a paper's trades in `asset` starting at or
+    /// after `from`, oldest first. A `limit` of 0 returns them all.
+    pub fn candles(
+        env: Env,
+        paper_id: u32,
+        asset: Address,
+        interval: CandleInterval,
+        from: u64,
+        limit: u32,
+    ) -> Vec<Candle> {
+        let mut out = Vec::new(&env);
+        for candle in read_candles(&env, paper_id, &asset, interval).iter() {
+            if limit != 0 && out.len() >= limit {
+                break;
+            }
+            if candle.start >= from {
+                out.push_back(candle);
+            }
+        }
+        out
+    }
+}
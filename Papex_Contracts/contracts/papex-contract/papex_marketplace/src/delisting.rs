@@ -0,0 +1,134 @@
+use soroban_sdk::{
+    contractclient, contractimpl, contracttype, symbol_short, Address, Env, Map, Symbol,
+};
+
+use crate::{book, PapexMarketplace, PapexMarketplaceClient};
+
+fn k_delistings() -> Symbol { symbol_short!("dlst") }
+
+/// Shortest notice and redemption windows a delisting may use.
+const MIN_NOTICE_PERIOD: u64 = 7 * 86_400;
+const MIN_REDEMPTION_PERIOD: u64 = 30 * 86_400;
+
+/// The paper token side of a delisting. The marketplace must be set as the
+/// token's delister for the call to succeed.
+#[contractclient(name = "DelistableTokenClient")]
+pub trait DelistableToken {
+    fn start_delisting(env: Env, caller: Address, notice_ends_at: u64, redemption_ends_at: u64);
+}
+
+/// Until `notice_ends_at` holders can only sell; from then until
+/// `redemption_ends_at` they redeem against the token's reserve. The listing
+/// can be archived once redemptions close.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ListingDelisting {
+    pub paper_id: u32,
+    pub notice_ends_at: u64,
+    pub redemption_ends_at: u64,
+    pub archived: bool,
+}
+
+fn delistings(env: &Env) -> Map<u32, ListingDelisting> {
+    env.storage()
+        .instance()
+        .get(&k_delistings())
+        .unwrap_or(Map::new(env))
+}
+
+fn write_delisting(env: &Env, delisting: &ListingDelisting) {
+    let mut all = delistings(env);
+    all.set(delisting.paper_id, delisting.clone());
+    env.storage().instance().set(&k_delistings(), &all);
+}
+
+/// Rejects anything that would buy into a listing being delisted.
+pub(crate) fn ensure_listed(env: &Env, paper_id: u32) {
+    if delistings(env).contains_key(paper_id) {
+        panic!("listing delisting");
+    }
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// Starts delisting a paper: closes its order book, crediting escrows for
+    /// their owners to withdraw, stops new buys and auction bids here and on
+    /// the token, and schedules the token's redemption window after
+    /// `notice_period` seconds. Admin or listing owner.
+    pub fn begin_delisting(
+        env: Env,
+        caller: Address,
+        paper_id: u32,
+        notice_period: u64,
+        redemption_period: u64,
+    ) -> ListingDelisting {
+        caller.require_auth();
+        let cfg = Self::read_cfg(&env);
+        let listing = Self::listings(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not found"));
+        if caller != cfg.admin && caller != listing.owner {
+            panic!("not authorized");
+        }
+        if !listing.is_active {
+            panic!("listing inactive");
+        }
+        ensure_listed(&env, paper_id);
+        if notice_period < MIN_NOTICE_PERIOD || redemption_period < MIN_REDEMPTION_PERIOD {
+            panic!("period too short");
+        }
+
+        let notice_ends_at = env.ledger().timestamp() + notice_period;
+        let delisting = ListingDelisting {
+            paper_id,
+            notice_ends_at,
+            redemption_ends_at: notice_ends_at + redemption_period,
+            archived: false,
+        };
+        write_delisting(&env, &delisting);
+        book::close_book(&env, paper_id);
+        DelistableTokenClient::new(&env, &listing.token).start_delisting(
+            &env.current_contract_address(),
+            &delisting.notice_ends_at,
+            &delisting.redemption_ends_at,
+        );
+
+        env.events().publish(
+            (symbol_short!("delist"),),
+            (paper_id, caller, delisting.notice_ends_at, delisting.redemption_ends_at),
+        );
+
+        delisting
+    }
+
+    pub fn get_delisting(env: Env, paper_id: u32) -> Option<ListingDelisting> {
+        delistings(&env).get(paper_id)
+    }
+
+    /// Deactivates a delisted listing for good once its redemption window
+    /// has closed. Callable by anyone.
+    pub fn archive_listing(env: Env, paper_id: u32) -> ListingDelisting {
+        let mut delisting = delistings(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not delisting"));
+        if delisting.archived {
+            panic!("listing archived");
+        }
+        if env.ledger().timestamp() < delisting.redemption_ends_at {
+            panic!("redemption window open");
+        }
+
+        let mut listings = Self::listings(&env);
+        let mut listing = listings.get(paper_id).unwrap();
+        listing.is_active = false;
+        listings.set(paper_id, listing);
+        Self::write_listings(&env, &listings);
+        delisting.archived = true;
+        write_delisting(&env, &delisting);
+
+        env.events()
+            .publish((symbol_short!("archive"),), (paper_id,));
+
+        delisting
+    }
+}
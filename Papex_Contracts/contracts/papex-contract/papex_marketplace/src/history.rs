@@ -0,0 +1,216 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Map, Symbol, Vec};
+
+use crate::{DataKey, PapexMarketplace, PapexMarketplaceClient, Trade};
+
+/// Instance key of the trimmed per-paper vectors used before trades moved to
+/// persistent storage. Only read by `migrate_trade_history`.
+fn k_legacy_trades() -> Symbol { symbol_short!("trd") }
+fn k_global_trade_count() -> Symbol { symbol_short!("gtrd") }
+
+/// Largest page `get_trades_page` returns.
+const MAX_TRADE_PAGE: u32 = 100;
+/// Trade entries are extended to roughly 180 days of ledgers once they fall
+/// below roughly 30 days.
+const TRADE_TTL_THRESHOLD: u32 = 518_400;
+const TRADE_TTL_EXTEND_TO: u32 = 3_110_400;
+
+/// A trade with its per-paper and global sequence numbers, both starting at
+/// zero.
+#[derive(Clone)]
+#[contracttype]
+pub struct TradeEntry {
+    pub paper_id: u32,
+    pub seq: u64,
+    pub global_seq: u64,
+    pub trade: Trade,
+}
+
+fn trade_count(env: &Env, paper_id: u32) -> u64 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::TradeCount(paper_id))
+        .unwrap_or(0)
+}
+
+fn global_trade_count(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&k_global_trade_count())
+        .unwrap_or(0)
+}
+
+fn read_entry(env: &Env, paper_id: u32, seq: u64) -> Option<TradeEntry> {
+    env.storage().persistent().get(&DataKey::Trade(paper_id, seq))
+}
+
+pub(crate) fn bump(env: &Env, key: &DataKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, TRADE_TTL_THRESHOLD, TRADE_TTL_EXTEND_TO);
+}
+
+/// Whether trades from before the persistent history still wait to be
+/// migrated.
+fn has_legacy(env: &Env) -> bool {
+    env.storage().instance().has(&k_legacy_trades())
+}
+
+/// Appends a trade. New trades are refused while legacy history is pending,
+/// since they would take sequence numbers ahead of older trades.
+pub(crate) fn append(env: &Env, paper_id: u32, trade: &Trade) -> TradeEntry {
+    if has_legacy(env) {
+        panic!("trade history not migrated");
+    }
+    push(env, paper_id, trade)
+}
+
+fn push(env: &Env, paper_id: u32, trade: &Trade) -> TradeEntry {
+    let seq = trade_count(env, paper_id);
+    let global_seq = global_trade_count(env);
+    let entry = TradeEntry {
+        paper_id,
+        seq,
+        global_seq,
+        trade: trade.clone(),
+    };
+
+    let keys = [
+        DataKey::Trade(paper_id, seq),
+        DataKey::TradeCount(paper_id),
+        DataKey::GlobalTrade(global_seq),
+    ];
+    let storage = env.storage().persistent();
+    storage.set(&keys[0], &entry);
+    storage.set(&keys[1], &(seq + 1));
+    storage.set(&keys[2], &(paper_id, seq));
+    for key in keys.iter() {
+        bump(env, key);
+    }
+    env.storage()
+        .instance()
+        .set(&k_global_trade_count(), &(global_seq + 1));
+
+    entry
+}
+
+/// `limit` capped to a page, with 0 meaning a full page like elsewhere.
+fn page_limit(limit: u32) -> u64 {
+    if limit == 0 {
+        MAX_TRADE_PAGE as u64
+    } else {
+        limit.min(MAX_TRADE_PAGE) as u64
+    }
+}
+
+/// The newest `limit` trades of a paper, oldest first.
+pub(crate) fn recent(env: &Env, paper_id: u32, limit: u32) -> Vec<Trade> {
+    let count = trade_count(env, paper_id);
+    let mut out = Vec::new(env);
+    for seq in count.saturating_sub(limit as u64)..count {
+        if let Option::Some(entry) = read_entry(env, paper_id, seq) {
+            out.push_back(entry.trade);
+        }
+    }
+    out
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    pub fn trade_count(env: Env, paper_id: u32) -> u64 {
+        trade_count(&env, paper_id)
+    }
+
+    pub fn global_trade_count(env: Env) -> u64 {
+        global_trade_count(&env)
+    }
+
+    /// Up to `limit` trades of a paper starting at `from_seq`, walking towards
+    /// newer trades or, with `newest_first`, towards older ones. Pass
+    /// `u64::MAX` with `newest_first` to start from the latest trade. A
+    /// `limit` of 0 returns a full page.
+    pub fn get_trades_page(
+        env: Env,
+        paper_id: u32,
+        from_seq: u64,
+        limit: u32,
+        newest_first: bool,
+    ) -> Vec<TradeEntry> {
+        let count = trade_count(&env, paper_id);
+        let limit = page_limit(limit);
+        let mut out = Vec::new(&env);
+        if count == 0 {
+            return out;
+        }
+
+        if newest_first {
+            let mut seq = from_seq.min(count - 1);
+            loop {
+                if let Option::Some(entry) = read_entry(&env, paper_id, seq) {
+                    out.push_back(entry);
+                }
+                if seq == 0 || out.len() as u64 >= limit {
+                    break;
+                }
+                seq -= 1;
+            }
+        } else {
+            for seq in from_seq..count.min(from_seq.saturating_add(limit)) {
+                if let Option::Some(entry) = read_entry(&env, paper_id, seq) {
+                    out.push_back(entry);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn get_trade_by_global_seq(env: Env, global_seq: u64) -> Option<TradeEntry> {
+        let (paper_id, seq): (u32, u64) = env
+            .storage()
+            .persistent()
+            .get(&DataKey::GlobalTrade(global_seq))?;
+        read_entry(&env, paper_id, seq)
+    }
+
+    /// Extends the storage lifetime of a range of a paper's trades. Callable
+    /// by anyone willing to pay for it.
+    pub fn extend_trades_ttl(env: Env, paper_id: u32, from_seq: u64, limit: u32) {
+        let count = trade_count(&env, paper_id);
+        let end = count.min(from_seq.saturating_add(page_limit(limit)));
+        for seq in from_seq..end {
+            let entry = read_entry(&env, paper_id, seq)
+                .unwrap_or_else(|| panic!("trade not found"));
+            bump(&env, &DataKey::Trade(paper_id, seq));
+            bump(&env, &DataKey::GlobalTrade(entry.global_seq));
+        }
+        if count > 0 {
+            bump(&env, &DataKey::TradeCount(paper_id));
+        }
+    }
+
+    /// Moves trades kept in the old instance-storage vectors into persistent
+    /// entries. New trades are refused until this has run after an upgrade,
+    /// so sequence numbers stay chronological. Admin only; a no-op once
+    /// migrated.
+    pub fn migrate_trade_history(env: Env, caller: Address) -> u32 {
+        Self::require_admin(&env, &caller);
+        let legacy: Option<Map<u32, Vec<Trade>>> =
+            env.storage().instance().get(&k_legacy_trades());
+        let legacy = match legacy {
+            Option::Some(legacy) => legacy,
+            Option::None => return 0,
+        };
+        let mut moved = 0u32;
+        for (paper_id, trades) in legacy.iter() {
+            for trade in trades.iter() {
+                push(&env, paper_id, &trade);
+                moved += 1;
+            }
+        }
+        env.storage().instance().remove(&k_legacy_trades());
+
+        env.events()
+            .publish((symbol_short!("trdmig"),), (caller, moved));
+
+        moved
+    }
+}
@@ -1,14 +1,62 @@
 #![no_std]
 
 use core::option::Option;
+use papex_common::{fees, pause};
 use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, Address, Env, Map, String, Symbol, Vec,
+    contract, contractimpl, contracttype, symbol_short, Address, BytesN, Env, Map, String,
+    Symbol, Vec,
 };
 
 fn k_cfg() -> Symbol { symbol_short!("cfg") }
 fn k_listings() -> Symbol { symbol_short!("lst") }
 fn k_listing_ids() -> Symbol { symbol_short!("ids") }
-fn k_trades() -> Symbol { symbol_short!("trd") }
+fn k_pending_admin() -> Symbol { symbol_short!("padm") }
+fn k_admin_delay() -> Symbol { symbol_short!("adly") }
+
+mod analytics;
+mod auction;
+mod book;
+mod candles;
+mod delisting;
+mod history;
+mod refunds;
+mod router;
+pub use analytics::{ListingStats, ListingVolume, TraderVolume};
+pub use auction::{Auction, AuctionKind};
+pub use book::BookOrder;
+pub use candles::{Candle, CandleInterval, PRICE_SCALE};
+pub use delisting::{DelistableToken, DelistableTokenClient, ListingDelisting};
+pub use history::TradeEntry;
+pub use router::{CurveQuote, PaperCurve, PaperCurveClient, RouteQuote};
+pub use papex_common::{FeeConfig, PauseController, PauseControllerClient};
+
+/// Persistent storage keys. Everything else lives in instance storage.
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    /// Trade `seq` of a paper.
+    Trade(u32, u64),
+    /// Number of trades recorded for a paper.
+    TradeCount(u32),
+    /// Paper id and per-paper sequence of a global sequence number.
+    GlobalTrade(u64),
+    /// Candle ring of a paper in one quote asset at one interval.
+    Candles(u32, Address, CandleInterval),
+    /// Running volume, trade and trader counts of a paper in one quote asset.
+    ListingTotals(u32, Address),
+    /// Marks an address as having traded a paper in one quote asset.
+    TraderSeen(u32, Address, Address),
+    /// All-time volume of a trader across papers, in one quote asset.
+    TraderVolume(Address, Address),
+    /// Amount of an asset owed to an address, e.g. an outbid auction bid.
+    Refund(Address, Address),
+    /// An auction, kept after it settles.
+    Auction(u32),
+    /// A resting order-book order.
+    BookOrder(u32),
+    /// Ids of a listing's resting orders, oldest first.
+    BookIndex(u32),
+}
 
 #[derive(Clone)]
 #[contracttype]
@@ -18,6 +66,14 @@ pub struct MarketplaceConfig {
     pub max_trade_history: u32,
 }
 
+/// Admin handover waiting for the proposed address to accept it.
+#[derive(Clone)]
+#[contracttype]
+pub struct PendingAdmin {
+    pub admin: Address,
+    pub effective_at: u64,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Listing {
@@ -61,6 +117,21 @@ impl PapexMarketplace {
         env.storage().instance().set(&k_cfg(), cfg);
     }
 
+    fn require_admin(env: &Env, caller: &Address) -> MarketplaceConfig {
+        let cfg = Self::read_cfg(env);
+        caller.require_auth();
+        if *caller != cfg.admin {
+            panic!("only admin");
+        }
+        cfg
+    }
+
+    fn ensure_not_paused(env: &Env) {
+        if pause::paused(env) {
+            panic!("paused");
+        }
+    }
+
     fn listings(env: &Env) -> Map<u32, Listing> {
         env.storage()
             .instance()
@@ -83,15 +154,24 @@ impl PapexMarketplace {
         env.storage().instance().set(&k_listing_ids(), ids);
     }
 
-    fn trades(env: &Env) -> Map<u32, Vec<Trade>> {
-        env.storage()
-            .instance()
-            .get(&k_trades())
-            .unwrap_or(Map::new(env))
-    }
-
-    fn write_trades(env: &Env, trades: &Map<u32, Vec<Trade>>) {
-        env.storage().instance().set(&k_trades(), trades);
+    /// Stores a trade as its own persistent entry in `paper_id`'s history.
+    /// `asset` is what `cost` is paid in; `counterparty` is the other trader
+    /// when both sides are known, e.g. the maker of a book fill.
+    fn append_trade(
+        env: &Env,
+        paper_id: u32,
+        trade: &Trade,
+        asset: &Address,
+        counterparty: Option<&Address>,
+    ) -> TradeEntry {
+        let entry = history::append(env, paper_id, trade);
+        candles::record(env, paper_id, trade, asset);
+        analytics::record(env, paper_id, trade, asset, counterparty);
+        env.events().publish(
+            (symbol_short!("trade"),),
+            (paper_id, trade.amount, trade.cost, trade.is_buy),
+        );
+        entry
     }
 }
 
@@ -115,9 +195,6 @@ impl PapexMarketplace {
         env.storage()
             .instance()
             .set(&k_listing_ids(), &Vec::<u32>::new(&env));
-        env.storage()
-            .instance()
-            .set(&k_trades(), &Map::<u32, Vec<Trade>>::new(&env));
 
         env.events()
             .publish((symbol_short!("init"),), (admin, cfg.max_trade_history));
@@ -137,6 +214,169 @@ impl PapexMarketplace {
         Self::write_cfg(&env, &cfg);
     }
 
+    /// Fee charged for creating a listing, if any.
+    pub fn fees(env: Env) -> Option<FeeConfig> {
+        fees::config(&env)
+    }
+
+    pub fn set_fee(env: Env, caller: Address, fee: Option<FeeConfig>) {
+        Self::require_admin(&env, &caller);
+        fees::set_config(&env, fee);
+    }
+
+    pub fn is_fee_waived(env: Env, who: Address) -> bool {
+        fees::is_waived(&env, &who)
+    }
+
+    /// Exempts an allow-listed institution from the fee.
+    pub fn set_fee_waiver(env: Env, caller: Address, who: Address, waived: bool) {
+        Self::require_admin(&env, &caller);
+        fees::set_waiver(&env, &who, waived);
+    }
+
+    /// True when this marketplace or its pause controller is paused.
+    pub fn is_paused(env: Env) -> bool {
+        pause::paused(&env)
+    }
+
+    pub fn pauser(env: Env) -> Option<Address> {
+        pause::pauser(&env)
+    }
+
+    pub fn pause_controller(env: Env) -> Option<Address> {
+        pause::controller(&env)
+    }
+
+    pub fn set_pauser(env: Env, caller: Address, pauser: Option<Address>) {
+        Self::require_admin(&env, &caller);
+        pause::set_pauser(&env, &caller, pauser);
+    }
+
+    /// Points the marketplace at a shared pause controller (for example the
+    /// registry). The controller must not consult this marketplace in turn.
+    pub fn set_pause_controller(env: Env, caller: Address, controller: Option<Address>) {
+        Self::require_admin(&env, &caller);
+        pause::set_controller(&env, &caller, controller);
+    }
+
+    /// Halts listing and trade recording. Callable by the admin or the pauser.
+    pub fn pause(env: Env, caller: Address) {
+        let cfg = Self::read_cfg(&env);
+        caller.require_auth();
+        let pauser = pause::pauser(&env);
+        if caller != cfg.admin && Option::Some(caller.clone()) != pauser {
+            panic!("not authorized");
+        }
+        pause::set_paused(&env, true);
+        env.events().publish((symbol_short!("pause"),), (caller,));
+    }
+
+    pub fn unpause(env: Env, caller: Address) {
+        Self::require_admin(&env, &caller);
+        pause::set_paused(&env, false);
+        env.events().publish((symbol_short!("unpause"),), (caller,));
+    }
+
+    /// Current admin handover delay, in seconds.
+    pub fn admin_delay(env: Env) -> u64 {
+        env.storage().instance().get(&k_admin_delay()).unwrap_or(0)
+    }
+
+    /// Sets how many seconds a proposed admin has to wait before
+    /// `accept_admin` succeeds.
+    pub fn set_admin_delay(env: Env, caller: Address, delay: u64) {
+        Self::require_admin(&env, &caller);
+        env.storage().instance().set(&k_admin_delay(), &delay);
+        env.events()
+            .publish((symbol_short!("adelay"),), (caller, delay));
+    }
+
+    pub fn pending_admin(env: Env) -> Option<PendingAdmin> {
+        env.storage().instance().get(&k_pending_admin())
+    }
+
+    /// First step of the handover: records `new_admin` as pending. A later
+    /// proposal replaces an earlier one.
+    pub fn propose_admin(env: Env, caller: Address, new_admin: Address) -> PendingAdmin {
+        Self::require_admin(&env, &caller);
+        let delay = Self::admin_delay(env.clone());
+        let pending = PendingAdmin {
+            admin: new_admin.clone(),
+            effective_at: env.ledger().timestamp().saturating_add(delay),
+        };
+        env.storage().instance().set(&k_pending_admin(), &pending);
+
+        env.events().publish(
+            (symbol_short!("adprop"),),
+            (caller, new_admin, pending.effective_at),
+        );
+
+        pending
+    }
+
+    pub fn cancel_admin_proposal(env: Env, caller: Address) {
+        Self::require_admin(&env, &caller);
+        let pending: PendingAdmin = env
+            .storage()
+            .instance()
+            .get(&k_pending_admin())
+            .unwrap_or_else(|| panic!("no pending admin"));
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events()
+            .publish((symbol_short!("adcncl"),), (caller, pending.admin));
+    }
+
+    /// Second step of the handover, signed by the proposed admin once the
+    /// timelock has elapsed.
+    pub fn accept_admin(env: Env, caller: Address) -> Address {
+        caller.require_auth();
+        let pending: PendingAdmin = env
+            .storage()
+            .instance()
+            .get(&k_pending_admin())
+            .unwrap_or_else(|| panic!("no pending admin"));
+        if caller != pending.admin {
+            panic!("not pending admin");
+        }
+        if env.ledger().timestamp() < pending.effective_at {
+            panic!("admin timelock active");
+        }
+
+        let mut cfg = Self::read_cfg(&env);
+        let previous = cfg.admin.clone();
+        cfg.admin = caller.clone();
+        Self::write_cfg(&env, &cfg);
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events()
+            .publish((symbol_short!("adacc"),), (previous, caller.clone()));
+
+        caller
+    }
+
+    /// Gives up admin rights for good. The admin slot is pointed at the
+    /// marketplace's own address, which never signs admin calls.
+    pub fn renounce_admin(env: Env, caller: Address) {
+        let mut cfg = Self::require_admin(&env, &caller);
+        cfg.admin = env.current_contract_address();
+        Self::write_cfg(&env, &cfg);
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events().publish((symbol_short!("adren"),), (caller,));
+    }
+
+    /// Replaces the contract code. Trades stay blocked afterwards until
+    /// `migrate_trade_history` has moved any legacy history.
+    pub fn upgrade(env: Env, caller: Address, new_wasm_hash: BytesN<32>) {
+        Self::require_admin(&env, &caller);
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+
+        env.events()
+            .publish((symbol_short!("upgrade"),), (caller, new_wasm_hash));
+    }
+
     pub fn register_listing(
         env: Env,
         caller: Address,
@@ -145,11 +385,13 @@ impl PapexMarketplace {
         metadata_uri: String,
     ) -> ListingRecord {
         caller.require_auth();
+        Self::ensure_not_paused(&env);
 
         let mut listings = Self::listings(&env);
         if listings.contains_key(paper_id) {
             panic!("listing exists");
         }
+        fees::charge(&env, &caller);
 
         let listing = Listing {
             paper_id,
@@ -193,6 +435,9 @@ impl PapexMarketplace {
         if caller != cfg.admin && caller != listing.owner {
             panic!("not authorized");
         }
+        if is_active {
+            delisting::ensure_listed(&env, paper_id);
+        }
 
         listing.is_active = is_active;
         listings.set(paper_id, listing.clone());
@@ -267,10 +512,12 @@ impl PapexMarketplace {
         out
     }
 
+    /// Records a trade settled elsewhere, with `cost` paid in `asset`.
     pub fn record_trade(
         env: Env,
         caller: Address,
         paper_id: u32,
+        asset: Address,
         amount: i128,
         cost: i128,
         is_buy: bool,
@@ -280,6 +527,7 @@ impl PapexMarketplace {
         }
         caller.require_auth();
         let cfg = Self::read_cfg(&env);
+        Self::ensure_not_paused(&env);
 
         let listings = Self::listings(&env);
         let listing = listings
@@ -298,37 +546,16 @@ impl PapexMarketplace {
             timestamp: env.ledger().timestamp(),
         };
 
-        let mut trades = Self::trades(&env);
-        let mut history = trades.get(paper_id).unwrap_or(Vec::new(&env));
-        history.push_back(trade.clone());
-
-        if history.len() > cfg.max_trade_history {
-            let mut trimmed = Vec::new(&env);
-            let keep_start = history.len() - cfg.max_trade_history;
-            let mut index = 0u32;
-            for entry in history.into_iter() {
-                if index >= keep_start {
-                    trimmed.push_back(entry);
-                }
-                index += 1;
-            }
-            history = trimmed;
-        }
-
-        trades.set(paper_id, history);
-        Self::write_trades(&env, &trades);
-
-        env.events().publish(
-            (symbol_short!("trade"),),
-            (paper_id, amount, cost, is_buy),
-        );
+        Self::append_trade(&env, paper_id, &trade, &asset, Option::None);
 
         trade
     }
 
+    /// The newest `max_trade_history` trades of a paper, oldest first. Older
+    /// trades stay available through `get_trades_page`.
     pub fn get_trades(env: Env, paper_id: u32) -> Vec<Trade> {
-        let trades = Self::trades(&env);
-        trades.get(paper_id).unwrap_or(Vec::new(&env))
+        let cfg = Self::read_cfg(&env);
+        history::recent(&env, paper_id, cfg.max_trade_history)
     }
 }
 
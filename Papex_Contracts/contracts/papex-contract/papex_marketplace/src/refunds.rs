@@ -0,0 +1,52 @@
+use soroban_sdk::{contractimpl, symbol_short, token, Address, Env};
+
+use crate::{history, DataKey, PapexMarketplace, PapexMarketplaceClient};
+
+fn balance(env: &Env, owner: &Address, asset: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Refund(owner.clone(), asset.clone()))
+        .unwrap_or(0)
+}
+
+/// Owes `amount` of `asset` to `owner`, who collects it with
+/// `withdraw_refund`. Used instead of transfers to third parties, so one
+/// recipient that cannot be paid does not block everyone else.
+pub(crate) fn credit(env: &Env, owner: &Address, asset: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    let key = DataKey::Refund(owner.clone(), asset.clone());
+    env.storage()
+        .persistent()
+        .set(&key, &(balance(env, owner, asset) + amount));
+    history::bump(env, &key);
+    env.events()
+        .publish((symbol_short!("credit"),), (owner.clone(), asset.clone(), amount));
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// Amount of `asset` waiting for `owner` to withdraw.
+    pub fn refund_of(env: Env, owner: Address, asset: Address) -> i128 {
+        balance(&env, &owner, &asset)
+    }
+
+    /// Pays out everything owed to `owner` in `asset`.
+    pub fn withdraw_refund(env: Env, owner: Address, asset: Address) -> i128 {
+        owner.require_auth();
+        let amount = balance(&env, &owner, &asset);
+        if amount == 0 {
+            panic!("nothing to withdraw");
+        }
+        env.storage()
+            .persistent()
+            .remove(&DataKey::Refund(owner.clone(), asset.clone()));
+        token::Client::new(&env, &asset).transfer(&env.current_contract_address(), &owner, &amount);
+
+        env.events()
+            .publish((symbol_short!("refund"),), (owner, asset, amount));
+
+        amount
+    }
+}
@@ -0,0 +1,160 @@
+use soroban_sdk::{contractclient, contractimpl, contracttype, symbol_short, Address, Env, Vec};
+
+use crate::{delisting, PapexMarketplace, PapexMarketplaceClient, Trade};
+
+/// Longest path `swap` accepts, counting the first and last token.
+const MAX_ROUTE_LEN: u32 = 4;
+
+/// Mirrors the paper token's `Quote`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct CurveQuote {
+    pub cost: i128,
+    pub price_before: i128,
+    pub price_after: i128,
+}
+
+/// The bonding-curve calls the router makes on each paper token.
+#[contractclient(name = "PaperCurveClient")]
+pub trait PaperCurve {
+    fn payment_token(env: Env) -> Option<Address>;
+    fn quote_buy(env: Env, amount: i128) -> CurveQuote;
+    fn quote_sell(env: Env, amount: i128) -> CurveQuote;
+    fn quote_buy_for_payment(env: Env, payment: i128) -> i128;
+    fn buy(env: Env, buyer: Address, amount: i128, max_payment: i128) -> CurveQuote;
+    fn sell(env: Env, seller: Address, amount: i128, min_payment: i128) -> CurveQuote;
+}
+
+/// `amounts[i]` is the amount of `path[i]`'s token going through the route:
+/// the input first and the output last. Each hop buys as much as the sale
+/// proceeds cover; what is left of them stays with the trader as `leftover`,
+/// in the shared payment asset.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct RouteQuote {
+    pub amounts: Vec<i128>,
+    pub leftover: i128,
+}
+
+/// Token contracts along `path` and their shared payment asset, once every
+/// paper in it is listed, active and priced in the same asset.
+fn route_tokens(env: &Env, path: &Vec<u32>) -> (Vec<Address>, Address) {
+    if path.len() < 2 || path.len() > MAX_ROUTE_LEN {
+        panic!("invalid route");
+    }
+    let listings = PapexMarketplace::listings(env);
+    let mut tokens = Vec::new(env);
+    let mut asset: Option<Address> = Option::None;
+    for (i, paper_id) in path.iter().enumerate() {
+        if path.iter().skip(i + 1).any(|other| other == paper_id) {
+            panic!("invalid route");
+        }
+        let listing = listings
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("listing not found"));
+        if !listing.is_active {
+            panic!("listing inactive");
+        }
+        // Delisting tokens can still be sold out of, never bought into.
+        if i > 0 {
+            delisting::ensure_listed(env, paper_id);
+        }
+        let payment = PaperCurveClient::new(env, &listing.token)
+            .payment_token()
+            .unwrap_or_else(|| panic!("no payment asset"));
+        match &asset {
+            Option::Some(expected) if *expected != payment => panic!("route assets differ"),
+            _ => asset = Option::Some(payment),
+        }
+        tokens.push_back(listing.token);
+    }
+    (tokens, asset.unwrap())
+}
+
+fn route_trade(env: &Env, trader: &Address, amount: i128, cost: i128, is_buy: bool) -> Trade {
+    Trade {
+        trader: trader.clone(),
+        amount,
+        cost,
+        is_buy,
+        timestamp: env.ledger().timestamp(),
+    }
+}
+
+#[contractimpl]
+impl PapexMarketplace {
+    /// What `swap` would return for `amount_in` of the first paper's token
+    /// right now.
+    pub fn quote_route(env: Env, path: Vec<u32>, amount_in: i128) -> RouteQuote {
+        if amount_in <= 0 {
+            panic!("invalid amount");
+        }
+        let (tokens, _) = route_tokens(&env, &path);
+        let mut amounts = Vec::from_array(&env, [amount_in]);
+        let mut leftover = 0;
+        let mut amount = amount_in;
+        for i in 1..tokens.len() {
+            let proceeds = PaperCurveClient::new(&env, &tokens.get(i - 1).unwrap())
+                .quote_sell(&amount)
+                .cost;
+            let to = PaperCurveClient::new(&env, &tokens.get(i).unwrap());
+            amount = to.quote_buy_for_payment(&proceeds);
+            if amount == 0 {
+                panic!("insufficient output");
+            }
+            leftover += proceeds - to.quote_buy(&amount).cost;
+            amounts.push_back(amount);
+        }
+        RouteQuote { amounts, leftover }
+    }
+
+    /// Sells `amount_in` of the first paper's token on its curve and rolls the
+    /// proceeds through each following curve in one transaction, reverting
+    /// unless at least `min_out` of the last token comes out. Buys pull the
+    /// payment from the trader just like a direct `buy` does. Every leg is
+    /// recorded in its paper's trade history.
+    pub fn swap(
+        env: Env,
+        trader: Address,
+        path: Vec<u32>,
+        amount_in: i128,
+        min_out: i128,
+    ) -> RouteQuote {
+        if amount_in <= 0 {
+            panic!("invalid amount");
+        }
+        trader.require_auth();
+        Self::ensure_not_paused(&env);
+        let (tokens, asset) = route_tokens(&env, &path);
+
+        let mut amounts = Vec::from_array(&env, [amount_in]);
+        let mut leftover = 0;
+        let mut amount = amount_in;
+        for i in 1..tokens.len() {
+            let sold = PaperCurveClient::new(&env, &tokens.get(i - 1).unwrap())
+                .sell(&trader, &amount, &0);
+            let sale = route_trade(&env, &trader, amount, sold.cost, false);
+            Self::append_trade(&env, path.get(i - 1).unwrap(), &sale, &asset, Option::None);
+
+            let to = PaperCurveClient::new(&env, &tokens.get(i).unwrap());
+            amount = to.quote_buy_for_payment(&sold.cost);
+            if amount == 0 {
+                panic!("insufficient output");
+            }
+            let bought = to.buy(&trader, &amount, &sold.cost);
+            let purchase = route_trade(&env, &trader, amount, bought.cost, true);
+            Self::append_trade(&env, path.get(i).unwrap(), &purchase, &asset, Option::None);
+
+            leftover += sold.cost - bought.cost;
+            amounts.push_back(amount);
+        }
+        if amount < min_out {
+            panic!("slippage");
+        }
+
+        env.events()
+            .publish((symbol_short!("swap"),), (trader, path, amount_in, amount));
+
+        RouteQuote { amounts, leftover }
+    }
+}
@@ -3,8 +3,9 @@
 use super::*;
 use core::option::Option;
 use soroban_sdk::{
-    testutils::{Address as _},
-    Address, Env, String,
+    contract, contractimpl,
+    testutils::{Address as _, Ledger},
+    token, vec, Address, Env, String,
 };
 
 #[test]
@@ -15,6 +16,7 @@ fn listing_and_trade_management() {
     let admin = Address::generate(&env);
     let author = Address::generate(&env);
     let token = Address::generate(&env);
+    let usd = Address::generate(&env);
     let new_owner = Address::generate(&env);
 
     let contract_id = env.register_contract(None, PapexMarketplace);
@@ -44,7 +46,7 @@ fn listing_and_trade_management() {
     let visible = client.list_listings(&10, &false);
     assert_eq!(visible.len(), 1);
 
-    let trade = client.record_trade(&new_owner, &7, &500, &2_000, &true);
+    let trade = client.record_trade(&new_owner, &7, &usd, &500, &2_000, &true);
     assert_eq!(trade.amount, 500);
     assert!(trade.is_buy);
 
@@ -53,3 +55,695 @@ fn listing_and_trade_management() {
     let first_trade = trades.get(0).unwrap();
     assert_eq!(first_trade.amount, 500);
 }
+
+#[test]
+fn admin_handover_with_timelock_and_renounce() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let successor = Address::generate(&env);
+    let stranger = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &0);
+
+    client.set_admin_delay(&admin, &600);
+    assert_eq!(client.admin_delay(), 600);
+    client.propose_admin(&admin, &stranger);
+    client.cancel_admin_proposal(&admin);
+    assert!(client.pending_admin().is_none());
+
+    let pending = client.propose_admin(&admin, &successor);
+    assert_eq!(pending.effective_at, 600);
+    assert!(client.try_accept_admin(&stranger).is_err());
+    assert!(client.try_accept_admin(&successor).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 600);
+    assert_eq!(client.accept_admin(&successor), successor);
+    assert_eq!(client.config().admin, successor);
+    assert!(client.try_set_registry(&admin, &Option::None).is_err());
+
+    client.renounce_admin(&successor);
+    assert_eq!(client.config().admin, contract_id);
+    assert!(client.try_set_registry(&successor, &Option::None).is_err());
+}
+
+#[test]
+fn shared_pause_controller_halts_listings_and_trades() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let token = Address::generate(&env);
+    let usd = Address::generate(&env);
+
+    let controller_id = env.register_contract(None, PapexMarketplace);
+    let controller = PapexMarketplaceClient::new(&env, &controller_id);
+    controller.init(&admin, &Option::None, &0);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &0);
+    client.set_pause_controller(&admin, &Option::Some(controller_id.clone()));
+
+    let uri = String::from_str(&env, "ipfs://paper/1");
+    client.register_listing(&author, &1, &token, &uri);
+
+    controller.pause(&admin);
+    assert!(client.is_paused());
+    assert!(client.try_register_listing(&author, &2, &token, &uri).is_err());
+    assert!(client.try_record_trade(&author, &1, &usd, &10, &100, &true).is_err());
+    assert_eq!(client.list_listings(&0, &true).len(), 1);
+
+    controller.unpause(&admin);
+    client.record_trade(&author, &1, &usd, &10, &100, &true);
+
+    client.pause(&admin);
+    assert!(client.try_record_trade(&author, &1, &usd, &10, &100, &true).is_err());
+    assert!(!controller.is_paused());
+}
+
+#[test]
+fn listing_fee_is_collected_unless_waived() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let author = Address::generate(&env);
+    let institution = Address::generate(&env);
+    let token = Address::generate(&env);
+
+    let asset = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    token::StellarAssetClient::new(&env, &asset).mint(&author, &100);
+    let asset_client = token::Client::new(&env, &asset);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &0);
+    client.set_fee(
+        &admin,
+        &Option::Some(FeeConfig {
+            asset: asset.clone(),
+            amount: 40,
+            treasury: treasury.clone(),
+        }),
+    );
+    client.set_fee_waiver(&admin, &institution, &true);
+
+    let uri = String::from_str(&env, "ipfs://paper/1");
+    client.register_listing(&author, &1, &token, &uri);
+    client.register_listing(&institution, &2, &token, &uri);
+    assert_eq!(asset_client.balance(&author), 60);
+    assert_eq!(asset_client.balance(&treasury), 40);
+
+    client.set_fee(&admin, &Option::None);
+    client.register_listing(&author, &3, &token, &uri);
+    assert_eq!(asset_client.balance(&author), 60);
+}
+
+#[test]
+fn order_book_matches_escrowed_bids_and_asks() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let issuer = Address::generate(&env);
+    let paper = env.register_stellar_asset_contract_v2(issuer.clone()).address();
+    let usd = env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(&env, &paper).mint(&seller, &100);
+    token::StellarAssetClient::new(&env, &usd).mint(&buyer, &10_000);
+    let paper_balance = token::Client::new(&env, &paper);
+    let usd_balance = token::Client::new(&env, &usd);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &20);
+    client.register_listing(&author, &7, &paper, &String::from_str(&env, "ipfs://paper/7"));
+    assert!(client.try_place_book_order(&seller, &7, &false, &50, &40, &0).is_err());
+    assert!(client.try_enable_order_book(&seller, &7, &usd, &100).is_err());
+    client.enable_order_book(&author, &7, &usd, &100);
+
+    let high_ask = client.place_book_order(&seller, &7, &false, &50, &40, &0);
+    client.place_book_order(&seller, &7, &false, &45, &20, &0);
+    assert_eq!(paper_balance.balance(&contract_id), 60);
+
+    // The bid sweeps the cheaper ask first and pays maker prices, credited
+    // to the maker to withdraw.
+    let bid = client.place_book_order(&buyer, &7, &true, &52, &50, &0);
+    assert_eq!(bid.remaining, 0);
+    assert_eq!(paper_balance.balance(&buyer), 50);
+    assert_eq!(usd_balance.balance(&buyer), 10_000 - 2_400);
+    assert_eq!(usd_balance.balance(&seller), 0);
+    assert_eq!(client.refund_of(&seller, &usd), 2_400);
+    assert_eq!(client.get_book_order(&high_ask.id).unwrap().remaining, 10);
+
+    let resting_bid = client.place_book_order(&buyer, &7, &true, &40, &5, &0);
+    assert_eq!(client.order_book(&7).len(), 2);
+    let ask = client.place_book_order(&seller, &7, &false, &38, &3, &0);
+    assert_eq!(ask.remaining, 0);
+    assert_eq!(usd_balance.balance(&seller), 120);
+    assert_eq!(client.refund_of(&buyer, &paper), 3);
+    assert_eq!(client.get_book_order(&resting_bid.id).unwrap().remaining, 2);
+    client.withdraw_refund(&seller, &usd);
+    client.withdraw_refund(&buyer, &paper);
+    assert_eq!(usd_balance.balance(&seller), 2_520);
+    assert_eq!(paper_balance.balance(&buyer), 53);
+
+    let trades = client.get_trades(&7);
+    assert_eq!(trades.len(), 3);
+    assert!(trades.get(0).unwrap().is_buy);
+    assert!(!trades.get(2).unwrap().is_buy);
+    assert_eq!(trades.get(2).unwrap().cost, 120);
+
+    assert!(client.try_cancel_book_order(&seller, &resting_bid.id).is_err());
+    client.cancel_book_order(&buyer, &resting_bid.id);
+    client.cancel_book_order(&seller, &high_ask.id);
+    assert_eq!(usd_balance.balance(&buyer), 10_000 - 2_400 - 120);
+    assert_eq!(paper_balance.balance(&seller), 100 - 53);
+    assert!(client.order_book(&7).is_empty());
+
+    // Dust is rejected, expired orders are pruned without matching and
+    // their escrow credited back, and each owner has a small quota.
+    assert!(client.try_place_book_order(&seller, &7, &false, &30, &3, &0).is_err());
+    let stale = client.place_book_order(&seller, &7, &false, &30, &4, &100);
+    env.ledger().with_mut(|li| li.timestamp = 100);
+    let open_bid = client.place_book_order(&buyer, &7, &true, &30, &4, &0);
+    assert_eq!(open_bid.remaining, 4);
+    assert!(client.get_book_order(&stale.id).is_none());
+    assert_eq!(client.refund_of(&seller, &paper), 4);
+    for _ in 0..5 {
+        client.place_book_order(&seller, &7, &false, &60, &2, &0);
+    }
+    assert!(client.try_place_book_order(&seller, &7, &false, &60, &2, &0).is_err());
+    assert_eq!(client.order_book(&7).len(), 6);
+
+    // Fills count for maker and taker alike.
+    assert_eq!(client.trader_volume(&buyer, &usd), 2_520);
+    assert_eq!(client.trader_volume(&seller, &usd), 2_520);
+    assert_eq!(client.listing_stats(&7, &usd).distinct_traders, 2);
+}
+
+#[test]
+fn english_and_dutch_auctions_settle_from_escrow() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let issuer = Address::generate(&env);
+    let paper = env.register_stellar_asset_contract_v2(issuer.clone()).address();
+    let usd = env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(&env, &paper).mint(&author, &100);
+    for who in [&alice, &bob] {
+        token::StellarAssetClient::new(&env, &usd).mint(who, &10_000);
+    }
+    let paper_balance = token::Client::new(&env, &paper);
+    let usd_balance = token::Client::new(&env, &usd);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &20);
+    client.register_listing(&author, &7, &paper, &String::from_str(&env, "ipfs://paper/7"));
+
+    let english = AuctionKind::English(1_000, 100);
+    assert!(client.try_create_auction(&alice, &7, &usd, &40, &english, &600).is_err());
+    let id = client.create_auction(&author, &7, &usd, &40, &english, &600);
+    assert_eq!(paper_balance.balance(&contract_id), 40);
+
+    assert!(client.try_place_bid(&alice, &id, &999).is_err());
+    client.place_bid(&alice, &id, &1_000);
+    assert!(client.try_place_bid(&bob, &id, &1_050).is_err());
+    client.place_bid(&bob, &id, &1_100);
+    assert_eq!(usd_balance.balance(&alice), 9_000);
+    assert_eq!(client.refund_of(&alice, &usd), 1_000);
+    assert_eq!(client.withdraw_refund(&alice, &usd), 1_000);
+    assert_eq!(usd_balance.balance(&alice), 10_000);
+    assert!(client.try_withdraw_refund(&alice, &usd).is_err());
+    assert!(client.try_cancel_auction(&author, &id).is_err());
+    assert!(client.try_settle_auction(&id).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 1_600);
+    assert!(client.try_place_bid(&alice, &id, &2_000).is_err());
+    client.settle_auction(&id);
+    assert_eq!(client.refund_of(&bob, &paper), 40);
+    assert_eq!(client.refund_of(&author, &usd), 1_100);
+    client.withdraw_refund(&bob, &paper);
+    assert_eq!(paper_balance.balance(&bob), 40);
+
+    // Dutch: 2,000 falling to 1,000 over 1,000 seconds.
+    let falling = AuctionKind::Dutch(2_000, 1_000);
+    let dutch = client.create_auction(&author, &7, &usd, &30, &falling, &1_000);
+    env.ledger().with_mut(|li| li.timestamp = 1_850);
+    assert_eq!(client.auction_price(&dutch), 1_750);
+    assert!(client.try_buy_auction(&alice, &dutch, &1_700).is_err());
+    client.buy_auction(&alice, &dutch, &1_750);
+    assert_eq!(paper_balance.balance(&alice), 30);
+    assert_eq!(client.withdraw_refund(&author, &usd), 2_850);
+    assert_eq!(usd_balance.balance(&author), 2_850);
+    assert!(client.get_auction(&dutch).unwrap().settled);
+
+    let unsold = client.create_auction(&author, &7, &usd, &30, &AuctionKind::Dutch(500, 100), &100);
+    env.ledger().with_mut(|li| li.timestamp = 2_000);
+    client.settle_auction(&unsold);
+    assert_eq!(paper_balance.balance(&author), 30);
+    assert_eq!(client.get_trades(&7).len(), 2);
+}
+
+#[test]
+fn trade_history_is_persistent_and_paginated() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let token = Address::generate(&env);
+    let usd = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &2);
+    client.register_listing(&author, &7, &token, &String::from_str(&env, "ipfs://paper/7"));
+    client.register_listing(&author, &8, &token, &String::from_str(&env, "ipfs://paper/8"));
+
+    for i in 1..=5 {
+        client.record_trade(&author, &7, &usd, &i, &(i * 100), &true);
+    }
+    client.record_trade(&author, &8, &usd, &9, &900, &false);
+
+    // `get_trades` shows the recent window; nothing older is dropped.
+    let recent = client.get_trades(&7);
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent.get(0).unwrap().amount, 4);
+    assert_eq!(client.trade_count(&7), 5);
+    assert_eq!(client.global_trade_count(), 6);
+
+    let page = client.get_trades_page(&7, &1, &3, &false);
+    assert_eq!(page.len(), 3);
+    assert_eq!(page.get(0).unwrap().seq, 1);
+    assert_eq!(page.get(2).unwrap().trade.amount, 4);
+
+    let newest = client.get_trades_page(&7, &u64::MAX, &2, &true);
+    assert_eq!(newest.get(0).unwrap().seq, 4);
+    assert_eq!(newest.get(1).unwrap().seq, 3);
+    assert_eq!(client.get_trades_page(&7, &0, &0, &false).len(), 5);
+    assert_eq!(client.get_trades_page(&7, &1, &10, &true).len(), 2);
+    assert!(client.get_trades_page(&9, &0, &10, &false).is_empty());
+
+    let other = client.get_trade_by_global_seq(&5).unwrap();
+    assert_eq!(other.paper_id, 8);
+    assert_eq!(other.seq, 0);
+    assert!(client.get_trade_by_global_seq(&6).is_none());
+
+    client.extend_trades_ttl(&7, &0, &5);
+    assert_eq!(client.migrate_trade_history(&admin), 0);
+
+    // Legacy history left by an upgrade blocks new trades until migrated.
+    client.register_listing(&author, &9, &token, &String::from_str(&env, "ipfs://paper/9"));
+    let legacy_trade = Trade {
+        trader: author.clone(),
+        amount: 3,
+        cost: 300,
+        is_buy: true,
+        timestamp: 0,
+    };
+    let legacy: Map<u32, Vec<Trade>> = Map::from_array(&env, [(9, vec![&env, legacy_trade])]);
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&symbol_short!("trd"), &legacy);
+    });
+    assert!(client.try_record_trade(&author, &9, &usd, &1, &100, &true).is_err());
+    assert!(client.try_migrate_trade_history(&author).is_err());
+    assert_eq!(client.migrate_trade_history(&admin), 1);
+    client.record_trade(&author, &9, &usd, &1, &100, &true);
+    assert_eq!(client.trade_count(&9), 2);
+    assert_eq!(client.global_trade_count(), 8);
+
+    let wasm_hash = BytesN::from_array(&env, &[0; 32]);
+    assert!(client.try_upgrade(&author, &wasm_hash).is_err());
+}
+
+#[test]
+fn trades_roll_up_into_hourly_and_daily_candles() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let token = Address::generate(&env);
+    let usd = Address::generate(&env);
+    let eur = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &0);
+    client.register_listing(&author, &7, &token, &String::from_str(&env, "ipfs://paper/7"));
+
+    // (timestamp, amount, cost): prices 10, 14, 8, 12 then 20 an hour later.
+    for (at, amount, cost) in [
+        (7_200, 10, 100),
+        (7_300, 5, 70),
+        (8_000, 10, 80),
+        (10_799, 2, 24),
+        (10_800, 1, 20),
+    ] {
+        env.ledger().with_mut(|li| li.timestamp = at);
+        client.record_trade(&author, &7, &usd, &amount, &cost, &true);
+    }
+
+    let hours = client.candles(&7, &usd, &CandleInterval::Hour, &0, &10);
+    assert_eq!(hours.len(), 2);
+    assert_eq!(
+        hours.get(0).unwrap(),
+        Candle {
+            start: 7_200,
+            open: 10 * PRICE_SCALE,
+            high: 14 * PRICE_SCALE,
+            low: 8 * PRICE_SCALE,
+            close: 12 * PRICE_SCALE,
+            volume: 27,
+            quote_volume: 274,
+            trades: 4,
+        }
+    );
+    assert_eq!(hours.get(1).unwrap().open, 20 * PRICE_SCALE);
+
+    let later = client.candles(&7, &usd, &CandleInterval::Hour, &7_201, &10);
+    assert_eq!(later.len(), 1);
+    assert_eq!(later.get(0).unwrap().start, 10_800);
+    assert_eq!(client.candles(&7, &usd, &CandleInterval::Hour, &0, &1).len(), 1);
+    assert_eq!(client.candles(&7, &usd, &CandleInterval::Hour, &0, &0), hours);
+
+    let days = client.candles(&7, &usd, &CandleInterval::Day, &0, &10);
+    assert_eq!(days.len(), 1);
+    assert_eq!(days.get(0).unwrap().close, 20 * PRICE_SCALE);
+    assert_eq!(days.get(0).unwrap().trades, 5);
+    assert!(client.candles(&8, &usd, &CandleInterval::Day, &0, &10).is_empty());
+
+    // Fills in another quote asset get candles of their own.
+    client.record_trade(&author, &7, &eur, &1, &500, &true);
+    assert_eq!(client.candles(&7, &usd, &CandleInterval::Day, &0, &10), days);
+    let eur_days = client.candles(&7, &eur, &CandleInterval::Day, &0, &10);
+    assert_eq!(eur_days.get(0).unwrap().close, 500 * PRICE_SCALE);
+    assert_eq!(eur_days.get(0).unwrap().trades, 1);
+
+    // Prices below one quote unit per token keep their fraction.
+    env.ledger().with_mut(|li| li.timestamp = 90_000);
+    client.record_trade(&author, &7, &usd, &3, &1, &true);
+    let cheap = client.candles(&7, &usd, &CandleInterval::Day, &86_400, &0);
+    assert_eq!(cheap.get(0).unwrap().close, PRICE_SCALE / 3);
+}
+
+#[test]
+fn analytics_track_volume_traders_and_leaderboards() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let token = Address::generate(&env);
+    let usd = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &0);
+    for paper_id in [7, 8, 9] {
+        client.register_listing(&author, &paper_id, &token, &String::from_str(&env, "ipfs://p"));
+    }
+    client.reassign_listing_owner(&admin, &9, &admin);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_000_000);
+    client.record_trade(&author, &7, &usd, &10, &1_000, &true);
+    env.ledger().with_mut(|li| li.timestamp = 1_000_000 + 3 * 86_400);
+    client.record_trade(&author, &7, &usd, &5, &600, &false);
+    client.record_trade(&admin, &7, &usd, &1, &50, &true);
+    client.record_trade(&author, &8, &usd, &4, &2_000, &true);
+    client.record_trade(&admin, &9, &usd, &1, &10, &true);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_000_000 + 3 * 86_400 + 3_600);
+    let stats = client.listing_stats(&7, &usd);
+    assert_eq!(stats.volume_all_time, 1_650);
+    assert_eq!(stats.volume_7d, 1_650);
+    assert_eq!(stats.volume_24h, 650);
+    assert_eq!(stats.trade_count, 3);
+    assert_eq!(stats.distinct_traders, 2);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_000_000 + 8 * 86_400);
+    let stats = client.listing_stats(&7, &usd);
+    assert_eq!(stats.volume_7d, 650);
+    assert_eq!(stats.volume_24h, 0);
+    assert_eq!(stats.volume_all_time, 1_650);
+
+    let top = client.top_listings(&usd, &2);
+    assert_eq!(top.len(), 2);
+    assert_eq!(top.get(0).unwrap(), ListingVolume { paper_id: 8, volume: 2_000 });
+    assert_eq!(top.get(1).unwrap().paper_id, 7);
+    assert_eq!(client.top_listings(&usd, &10).len(), 3);
+
+    let traders = client.top_traders(&usd, &5);
+    assert_eq!(traders.len(), 2);
+    assert_eq!(traders.get(0).unwrap(), TraderVolume { trader: author.clone(), volume: 3_600 });
+    assert_eq!(client.trader_volume(&admin, &usd), 60);
+
+    // Volume in another quote asset is kept apart.
+    let eur = Address::generate(&env);
+    client.record_trade(&admin, &9, &eur, &1, &10_000, &true);
+    assert_eq!(client.trader_volume(&admin, &usd), 60);
+    assert_eq!(client.trader_volume(&admin, &eur), 10_000);
+    assert_eq!(client.top_traders(&eur, &5).len(), 1);
+    assert_eq!(client.top_traders(&usd, &5).len(), 2);
+    assert_eq!(client.listing_stats(&9, &eur).volume_all_time, 10_000);
+    assert_eq!(client.listing_stats(&9, &usd).volume_all_time, 10);
+    assert_eq!(client.top_listings(&eur, &10), client.top_listings(&eur, &1));
+    assert_eq!(client.top_listings(&usd, &1).get(0).unwrap().paper_id, 8);
+}
+
+/// Fixed-price stand-in for a paper token's bonding curve.
+#[contract]
+struct MockCurve;
+
+#[contractimpl]
+impl MockCurve {
+    pub fn setup(env: Env, payment: Address, price: i128) {
+        env.storage().instance().set(&symbol_short!("pay"), &payment);
+        env.storage().instance().set(&symbol_short!("price"), &price);
+    }
+
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let balance = Self::balance_of(env.clone(), to.clone());
+        env.storage().instance().set(&to, &(balance + amount));
+    }
+
+    pub fn balance_of(env: Env, who: Address) -> i128 {
+        env.storage().instance().get(&who).unwrap_or(0)
+    }
+
+    pub fn payment_token(env: Env) -> Option<Address> {
+        env.storage().instance().get(&symbol_short!("pay"))
+    }
+
+    pub fn quote_buy(env: Env, amount: i128) -> CurveQuote {
+        let price: i128 = env.storage().instance().get(&symbol_short!("price")).unwrap();
+        CurveQuote { cost: price * amount, price_before: price, price_after: price }
+    }
+
+    pub fn quote_sell(env: Env, amount: i128) -> CurveQuote {
+        Self::quote_buy(env, amount)
+    }
+
+    pub fn quote_buy_for_payment(env: Env, payment: i128) -> i128 {
+        let price: i128 = env.storage().instance().get(&symbol_short!("price")).unwrap();
+        payment / price
+    }
+
+    pub fn buy(env: Env, buyer: Address, amount: i128, max_payment: i128) -> CurveQuote {
+        buyer.require_auth();
+        let quote = Self::quote_buy(env.clone(), amount);
+        assert!(quote.cost <= max_payment);
+        let payment = Self::payment_token(env.clone()).unwrap();
+        token::Client::new(&env, &payment).transfer(
+            &buyer,
+            &env.current_contract_address(),
+            &quote.cost,
+        );
+        Self::mint(env, buyer, amount);
+        quote
+    }
+
+    pub fn sell(env: Env, seller: Address, amount: i128, min_payment: i128) -> CurveQuote {
+        seller.require_auth();
+        let quote = Self::quote_sell(env.clone(), amount);
+        assert!(quote.cost >= min_payment);
+        Self::mint(env.clone(), seller.clone(), -amount);
+        let payment = Self::payment_token(env.clone()).unwrap();
+        token::Client::new(&env, &payment).transfer(
+            &env.current_contract_address(),
+            &seller,
+            &quote.cost,
+        );
+        quote
+    }
+}
+
+#[test]
+fn swaps_route_proceeds_through_several_curves() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let trader = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let other = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let curves = [10i128, 3, 7, 5].map(|price| {
+        let id = env.register_contract(None, MockCurve);
+        MockCurveClient::new(&env, &id).setup(&payment, &price);
+        id
+    });
+    MockCurveClient::new(&env, &curves[3]).setup(&other, &5);
+    token::StellarAssetClient::new(&env, &payment).mint(&curves[0], &1_000);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &20);
+    for (i, curve) in curves.iter().enumerate() {
+        let uri = String::from_str(&env, "ipfs://p");
+        client.register_listing(&author, &(i as u32 + 1), curve, &uri);
+    }
+
+    let curve_a = MockCurveClient::new(&env, &curves[0]);
+    let curve_c = MockCurveClient::new(&env, &curves[2]);
+    curve_a.mint(&trader, &10);
+
+    // 10 A -> 100 -> 33 B (1 left) -> 99 -> 14 C (1 left)
+    let path = vec![&env, 1u32, 2, 3];
+    let quote = client.quote_route(&path, &10);
+    assert_eq!(quote.amounts, vec![&env, 10i128, 33, 14]);
+    assert_eq!(quote.leftover, 2);
+
+    assert!(client.try_swap(&trader, &path, &10, &15).is_err());
+    assert!(client.try_swap(&trader, &vec![&env, 1u32, 2, 1], &10, &0).is_err());
+    assert!(client.try_swap(&trader, &vec![&env, 1u32, 4], &10, &0).is_err());
+    assert!(client.try_swap(&trader, &vec![&env, 1u32], &10, &0).is_err());
+
+    let swapped = client.swap(&trader, &path, &10, &14);
+    assert_eq!(swapped.amounts, quote.amounts);
+    assert_eq!(curve_a.balance_of(&trader), 0);
+    assert_eq!(MockCurveClient::new(&env, &curves[1]).balance_of(&trader), 0);
+    assert_eq!(curve_c.balance_of(&trader), 14);
+    assert_eq!(token::Client::new(&env, &payment).balance(&trader), 2);
+
+    assert_eq!(client.trade_count(&1), 1);
+    assert_eq!(client.trade_count(&2), 2);
+    assert_eq!(client.trade_count(&3), 1);
+    assert_eq!(client.trader_volume(&trader, &payment), 100 + 99 + 99 + 98);
+}
+
+/// Records the schedule a delisting hands to the paper token, and keeps
+/// plain balances so the paper can be auctioned.
+#[contract]
+struct MockDelistable;
+
+#[contractimpl]
+impl MockDelistable {
+    pub fn issue(env: Env, to: Address, amount: i128) {
+        let balance: i128 = env.storage().instance().get(&to).unwrap_or(0);
+        env.storage().instance().set(&to, &(balance + amount));
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        let balance: i128 = env.storage().instance().get(&from).unwrap_or(0);
+        assert!(balance >= amount);
+        env.storage().instance().set(&from, &(balance - amount));
+        Self::issue(env, to, amount);
+    }
+
+    pub fn start_delisting(
+        env: Env,
+        caller: Address,
+        notice_ends_at: u64,
+        redemption_ends_at: u64,
+    ) {
+        caller.require_auth();
+        let schedule = (caller, notice_ends_at, redemption_ends_at);
+        env.storage().instance().set(&symbol_short!("dlst"), &schedule);
+    }
+
+    pub fn schedule(env: Env) -> (Address, u64, u64) {
+        env.storage().instance().get(&symbol_short!("dlst")).unwrap()
+    }
+}
+
+#[test]
+fn delisting_closes_the_book_and_archives_after_redemption() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let stranger = Address::generate(&env);
+
+    let usd = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let usd_client = token::Client::new(&env, &usd);
+    token::StellarAssetClient::new(&env, &usd).mint(&buyer, &1_000);
+    let paper = env.register_contract(None, MockDelistable);
+
+    let contract_id = env.register_contract(None, PapexMarketplace);
+    let client = PapexMarketplaceClient::new(&env, &contract_id);
+    client.init(&admin, &Option::None, &20);
+    client.register_listing(&author, &7, &paper, &String::from_str(&env, "ipfs://p"));
+    client.enable_order_book(&author, &7, &usd, &100);
+    client.place_book_order(&buyer, &7, &true, &10, &50, &0);
+    assert_eq!(usd_client.balance(&buyer), 500);
+    MockDelistableClient::new(&env, &paper).issue(&author, &5);
+    let english = AuctionKind::English(100, 10);
+    let auction = client.create_auction(&author, &7, &usd, &5, &english, &86_400);
+    client.place_bid(&buyer, &auction, &100);
+
+    let week = 7 * 86_400;
+    let month = 30 * 86_400;
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+    assert!(client.try_begin_delisting(&stranger, &7, &week, &month).is_err());
+    assert!(client.try_begin_delisting(&author, &7, &(week - 1), &month).is_err());
+    let delisting = client.begin_delisting(&author, &7, &week, &month);
+    assert_eq!(delisting.notice_ends_at, 1_000 + week);
+    assert_eq!(delisting.redemption_ends_at, 1_000 + week + month);
+    assert_eq!(
+        MockDelistableClient::new(&env, &paper).schedule(),
+        (contract_id.clone(), 1_000 + week, 1_000 + week + month)
+    );
+    assert!(client.try_begin_delisting(&admin, &7, &week, &month).is_err());
+
+    // Resting orders are credited back, to be withdrawn, and no new buying
+    // is accepted, auction bids included.
+    assert_eq!(client.order_book(&7).len(), 0);
+    assert_eq!(usd_client.balance(&buyer), 400);
+    assert_eq!(client.refund_of(&buyer, &usd), 500);
+    client.withdraw_refund(&buyer, &usd);
+    assert_eq!(usd_client.balance(&buyer), 900);
+    assert!(client.try_place_book_order(&buyer, &7, &true, &10, &10, &0).is_err());
+    assert!(client.try_create_auction(&author, &7, &usd, &1, &english, &600).is_err());
+    assert!(client.try_place_bid(&stranger, &auction, &110).is_err());
+    client.update_listing_status(&author, &7, &false);
+    assert!(client.try_update_listing_status(&author, &7, &true).is_err());
+    client.update_listing_status(&author, &7, &false);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_000 + week + month - 1);
+    assert!(client.try_archive_listing(&7).is_err());
+    env.ledger().with_mut(|li| li.timestamp = 1_000 + week + month);
+    assert!(client.archive_listing(&7).archived);
+    assert!(!client.get_listing(&7).unwrap().data.is_active);
+    assert!(client.get_delisting(&7).unwrap().archived);
+    assert!(client.try_archive_listing(&7).is_err());
+}
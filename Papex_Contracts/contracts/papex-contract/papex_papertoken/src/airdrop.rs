@@ -0,0 +1,209 @@
+use soroban_sdk::{
+    contractimpl, contracttype, symbol_short, xdr::ToXdr, Address, Bytes, BytesN, Env, Map,
+    Symbol, Vec,
+};
+
+use crate::{delisting, merkle, PapexToken, PapexTokenClient};
+
+fn k_airdrops() -> Symbol { symbol_short!("adrop") }
+fn k_airdrop_reserved() -> Symbol { symbol_short!("adres") }
+fn k_claim_bits() -> Symbol { symbol_short!("adbit") }
+fn k_unbacked() -> Symbol { symbol_short!("adunb") }
+
+/// Merkle distribution of newly minted tokens. Leaves are
+/// `sha256(index_u32_be || claimant_xdr || amount_i128_be)`; the unclaimed
+/// part of `pool` stays reserved out of `max_supply` until the round closes.
+/// Claimed tokens bring no liquidity, so they count as unbacked supply.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Airdrop {
+    pub id: u32,
+    pub root: BytesN<32>,
+    pub pool: i128,
+    pub claimed: i128,
+    pub closed: bool,
+}
+
+fn airdrops(env: &Env) -> Vec<Airdrop> {
+    env.storage()
+        .instance()
+        .get(&k_airdrops())
+        .unwrap_or(Vec::new(env))
+}
+
+fn read_airdrop(env: &Env, airdrop_id: u32) -> Airdrop {
+    airdrops(env)
+        .get(airdrop_id)
+        .unwrap_or_else(|| panic!("airdrop not found"))
+}
+
+fn write_airdrop(env: &Env, airdrop: &Airdrop) {
+    let mut all = airdrops(env);
+    all.set(airdrop.id, airdrop.clone());
+    env.storage().instance().set(&k_airdrops(), &all);
+}
+
+/// Supply held back for open airdrop rounds.
+pub(crate) fn reserved(env: &Env) -> i128 {
+    env.storage().instance().get(&k_airdrop_reserved()).unwrap_or(0)
+}
+
+fn write_reserved(env: &Env, amount: i128) {
+    env.storage().instance().set(&k_airdrop_reserved(), &amount);
+}
+
+/// Claimed airdrop supply, which paid nothing into liquidity.
+pub(crate) fn unbacked(env: &Env) -> i128 {
+    env.storage().instance().get(&k_unbacked()).unwrap_or(0)
+}
+
+pub(crate) fn write_unbacked(env: &Env, amount: i128) {
+    env.storage().instance().set(&k_unbacked(), &amount);
+}
+
+fn claim_bits(env: &Env) -> Map<(u32, u32), u64> {
+    env.storage()
+        .instance()
+        .get(&k_claim_bits())
+        .unwrap_or(Map::new(env))
+}
+
+fn is_claimed(env: &Env, airdrop_id: u32, index: u32) -> bool {
+    let word = claim_bits(env).get((airdrop_id, index / 64)).unwrap_or(0);
+    word & (1 << (index % 64)) != 0
+}
+
+fn leaf(env: &Env, index: u32, claimant: &Address, amount: i128) -> BytesN<32> {
+    let mut preimage = Bytes::from_array(env, &index.to_be_bytes());
+    preimage.append(&claimant.clone().to_xdr(env));
+    preimage.append(&Bytes::from_array(env, &amount.to_be_bytes()));
+    env.crypto().sha256(&preimage).to_bytes()
+}
+
+/// Opens a round, for the owner or an executed proposal.
+pub(crate) fn start(env: &Env, root: &BytesN<32>, pool: i128) -> u32 {
+    let cfg = PapexToken::read_cfg(env);
+    let committed = PapexToken::read_total_supply(env) + reserved(env);
+    if committed + pool > cfg.max_supply {
+        panic!("exceeds max supply");
+    }
+    write_reserved(env, reserved(env) + pool);
+
+    let airdrop_id = airdrops(env).len();
+    let mut all = airdrops(env);
+    all.push_back(Airdrop {
+        id: airdrop_id,
+        root: root.clone(),
+        pool,
+        claimed: 0,
+        closed: false,
+    });
+    env.storage().instance().set(&k_airdrops(), &all);
+
+    env.events()
+        .publish((symbol_short!("adrop"),), (airdrop_id, root.clone(), pool));
+
+    airdrop_id
+}
+
+/// Closes a round, for the owner or an executed proposal.
+pub(crate) fn close(env: &Env, airdrop_id: u32) -> i128 {
+    let mut airdrop = read_airdrop(env, airdrop_id);
+    if airdrop.closed {
+        panic!("airdrop closed");
+    }
+    airdrop.closed = true;
+    write_airdrop(env, &airdrop);
+
+    let unclaimed = airdrop.pool - airdrop.claimed;
+    write_reserved(env, reserved(env) - unclaimed);
+
+    env.events()
+        .publish((symbol_short!("adclose"),), (airdrop_id, unclaimed));
+
+    unclaimed
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Commits a Merkle root for a new airdrop round and reserves `pool`
+    /// tokens for it. Airdropped tokens add no liquidity, so they leave the
+    /// curve price where it is and share in redemptions only pro rata.
+    pub fn start_airdrop(env: Env, caller: Address, root: BytesN<32>, pool: i128) -> u32 {
+        Self::ensure_amount(pool);
+        Self::require_owner_call(&env, &caller);
+        start(&env, &root, pool)
+    }
+
+    pub fn get_airdrop(env: Env, airdrop_id: u32) -> Option<Airdrop> {
+        airdrops(&env).get(airdrop_id)
+    }
+
+    pub fn airdrop_reserved(env: Env) -> i128 {
+        reserved(&env)
+    }
+
+    pub fn airdrop_unbacked(env: Env) -> i128 {
+        unbacked(&env)
+    }
+
+    pub fn is_claimed(env: Env, airdrop_id: u32, index: u32) -> bool {
+        is_claimed(&env, airdrop_id, index)
+    }
+
+    /// Mints `claimant`'s allocation at leaf `index` of the round's tree.
+    pub fn claim(
+        env: Env,
+        claimant: Address,
+        airdrop_id: u32,
+        index: u32,
+        amount: i128,
+        proof: Vec<BytesN<32>>,
+    ) -> i128 {
+        Self::ensure_amount(amount);
+        claimant.require_auth();
+        Self::ensure_not_paused(&env);
+        delisting::ensure_listed(&env);
+
+        let mut airdrop = read_airdrop(&env, airdrop_id);
+        if airdrop.closed {
+            panic!("airdrop closed");
+        }
+        if is_claimed(&env, airdrop_id, index) {
+            panic!("already claimed");
+        }
+        if !merkle::verify(&env, &airdrop.root, leaf(&env, index, &claimant, amount), &proof) {
+            panic!("invalid proof");
+        }
+        if airdrop.claimed + amount > airdrop.pool {
+            panic!("airdrop pool exhausted");
+        }
+
+        let mut bits = claim_bits(&env);
+        let word = bits.get((airdrop_id, index / 64)).unwrap_or(0);
+        bits.set((airdrop_id, index / 64), word | (1 << (index % 64)));
+        env.storage().instance().set(&k_claim_bits(), &bits);
+
+        airdrop.claimed += amount;
+        write_airdrop(&env, &airdrop);
+        write_reserved(&env, reserved(&env) - amount);
+        write_unbacked(&env, unbacked(&env) + amount);
+
+        Self::write_total_supply(&env, Self::read_total_supply(&env) + amount);
+        let mut balances = Self::read_balances(&env);
+        let current = balances.get(claimant.clone()).unwrap_or(0);
+        Self::set_balance(&env, &mut balances, &claimant, current + amount);
+        Self::write_balances(&env, &balances);
+
+        env.events()
+            .publish((symbol_short!("adclaim"),), (airdrop_id, index, claimant, amount));
+
+        amount
+    }
+
+    /// Ends a round and releases its unclaimed reservation.
+    pub fn close_airdrop(env: Env, caller: Address, airdrop_id: u32) -> i128 {
+        Self::require_owner_call(&env, &caller);
+        close(&env, airdrop_id)
+    }
+}
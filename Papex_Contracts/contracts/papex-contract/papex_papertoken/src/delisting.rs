@@ -0,0 +1,189 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Symbol};
+
+use crate::{airdrop, PapexToken, PapexTokenClient};
+
+fn k_delister() -> Symbol { symbol_short!("dlctl") }
+fn k_delisting() -> Symbol { symbol_short!("dlst") }
+
+/// Shortest notice and redemption windows a delister may set, matching the
+/// marketplace's.
+const MIN_NOTICE_PERIOD: u64 = 7 * 86_400;
+const MIN_REDEMPTION_PERIOD: u64 = 30 * 86_400;
+
+/// Wind-down schedule set by the delister. Until `notice_ends_at` holders
+/// can still sell on the curve; after that they redeem pro rata against the
+/// remaining liquidity instead. `redemption_ends_at` only marks when the
+/// listing may be archived: redemptions stay open for good.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Delisting {
+    pub started_at: u64,
+    pub notice_ends_at: u64,
+    pub redemption_ends_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum DelistPhase {
+    Listed,
+    Notice,
+    Redemption,
+    Closed,
+}
+
+/// Sets or clears the delister, for the owner or an executed proposal.
+pub(crate) fn set_delister(env: &Env, delister: &Option<Address>) {
+    match delister {
+        Option::Some(d) => env.storage().instance().set(&k_delister(), d),
+        Option::None => env.storage().instance().remove(&k_delister()),
+    }
+}
+
+fn read_delisting(env: &Env) -> Option<Delisting> {
+    env.storage().instance().get(&k_delisting())
+}
+
+pub(crate) fn phase(env: &Env) -> DelistPhase {
+    let now = env.ledger().timestamp();
+    match read_delisting(env) {
+        Option::None => DelistPhase::Listed,
+        Option::Some(d) if now < d.notice_ends_at => DelistPhase::Notice,
+        Option::Some(d) if now < d.redemption_ends_at => DelistPhase::Redemption,
+        Option::Some(_) => DelistPhase::Closed,
+    }
+}
+
+/// Anything that adds holders or supply stops once delisting starts.
+pub(crate) fn ensure_listed(env: &Env) {
+    if phase(env) != DelistPhase::Listed {
+        panic!("delisting");
+    }
+}
+
+/// Curve sells stay open through the notice period.
+pub(crate) fn ensure_sellable(env: &Env) {
+    if matches!(phase(env), DelistPhase::Redemption | DelistPhase::Closed) {
+        panic!("delisted");
+    }
+}
+
+/// Once delisting starts, liquidity only ever leaves through sells and
+/// redemptions, never back to the owner.
+pub(crate) fn reserve_locked(env: &Env) -> bool {
+    phase(env) != DelistPhase::Listed
+}
+
+fn redemption_value(env: &Env, amount: i128) -> i128 {
+    let supply = PapexToken::read_total_supply(env);
+    if supply == 0 {
+        return 0;
+    }
+    PapexToken::read_liquidity(env) * amount / supply
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Lets `delister` (typically the marketplace) start a delisting. Once
+    /// governance is enabled this takes a `SetDelister` proposal.
+    pub fn set_delister(env: Env, caller: Address, delister: Option<Address>) {
+        Self::require_owner_call(&env, &caller);
+        set_delister(&env, &delister);
+    }
+
+    pub fn delister(env: Env) -> Option<Address> {
+        env.storage().instance().get(&k_delister())
+    }
+
+    /// Stops buys right away, keeps sells open until `notice_ends_at` and then
+    /// opens redemptions. The notice must last at least 7 days and the
+    /// redemption window at least 30, whoever the delister is. Cannot be
+    /// undone.
+    pub fn start_delisting(
+        env: Env,
+        caller: Address,
+        notice_ends_at: u64,
+        redemption_ends_at: u64,
+    ) {
+        caller.require_auth();
+        if Self::delister(env.clone()) != Option::Some(caller) {
+            panic!("only delister");
+        }
+        if read_delisting(&env).is_some() {
+            panic!("already delisting");
+        }
+        let now = env.ledger().timestamp();
+        if notice_ends_at < now.saturating_add(MIN_NOTICE_PERIOD)
+            || redemption_ends_at < notice_ends_at.saturating_add(MIN_REDEMPTION_PERIOD)
+        {
+            panic!("invalid schedule");
+        }
+        let delisting = Delisting {
+            started_at: now,
+            notice_ends_at,
+            redemption_ends_at,
+        };
+        env.storage().instance().set(&k_delisting(), &delisting);
+
+        env.events()
+            .publish((symbol_short!("delist"),), (notice_ends_at, redemption_ends_at));
+    }
+
+    pub fn delisting(env: Env) -> Option<Delisting> {
+        read_delisting(&env)
+    }
+
+    pub fn delisting_phase(env: Env) -> DelistPhase {
+        phase(&env)
+    }
+
+    /// What redeeming `amount` pays at the current rate.
+    pub fn quote_redeem(env: Env, amount: i128) -> i128 {
+        Self::ensure_amount(amount);
+        redemption_value(&env, amount)
+    }
+
+    /// Burns `amount` once the notice period is over for the same share of
+    /// the remaining liquidity, so every holder exits at the same rate
+    /// whenever they redeem. Unbacked airdrop supply counts in the total and
+    /// redeems at that rate too, never ahead of tokens that were paid for.
+    /// Pausing does not stop redemptions, and they stay open after the
+    /// window so no holder's share is ever left behind.
+    pub fn redeem(env: Env, holder: Address, amount: i128) -> i128 {
+        Self::ensure_amount(amount);
+        holder.require_auth();
+        if !matches!(phase(&env), DelistPhase::Redemption | DelistPhase::Closed) {
+            panic!("redemption closed");
+        }
+
+        let mut balances = Self::read_balances(&env);
+        let current = balances.get(holder.clone()).unwrap_or(0);
+        if current < amount {
+            panic!("insufficient balance");
+        }
+        let supply = Self::read_total_supply(&env);
+        let liquidity = Self::read_liquidity(&env);
+        let payout = redemption_value(&env, amount);
+
+        Self::set_balance(&env, &mut balances, &holder, current - amount);
+        Self::write_balances(&env, &balances);
+        // Burns leave the backed share of the supply where it was.
+        let unbacked = airdrop::unbacked(&env);
+        airdrop::write_unbacked(&env, unbacked - unbacked * amount / supply);
+        Self::write_total_supply(&env, supply - amount);
+        Self::write_liquidity(&env, liquidity - payout);
+
+        let cfg = Self::read_cfg(&env);
+        if let Option::Some(token) = cfg.payment_token {
+            Self::token_client(&env, &token).transfer(
+                &env.current_contract_address(),
+                &holder,
+                &payout,
+            );
+        }
+
+        env.events()
+            .publish((symbol_short!("redeem"),), (holder, amount, payout));
+
+        payout
+    }
+}
@@ -0,0 +1,63 @@
+use soroban_sdk::{contractimpl, symbol_short, Address, Env, Symbol, Vec};
+
+use crate::{rewards, PapexToken, PapexTokenClient};
+
+fn k_escrows() -> Symbol { symbol_short!("escr") }
+
+fn escrows(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&k_escrows())
+        .unwrap_or(Vec::new(env))
+}
+
+/// Accounts holding tokens on behalf of others: this contract, for limit
+/// orders, and any account the owner registers, such as a marketplace
+/// holding book and auction escrow.
+pub(crate) fn is_escrow(env: &Env, who: &Address) -> bool {
+    *who == env.current_contract_address() || escrows(env).contains(who)
+}
+
+/// Tokens currently held by escrow accounts.
+pub(crate) fn escrowed_supply(env: &Env) -> i128 {
+    let balances = PapexToken::read_balances(env);
+    let held = |who: Address| balances.get(who).unwrap_or(0);
+    escrows(env).iter().map(held).sum::<i128>() + held(env.current_contract_address())
+}
+
+/// Applies an escrow registration, for the owner or an executed proposal.
+pub(crate) fn set_escrow(env: &Env, account: &Address, is_escrow: bool) {
+    if *account == env.current_contract_address() {
+        panic!("invalid account");
+    }
+    // Settle under the old status so rewards follow the balance's past.
+    let balance = PapexToken::balance_of(env.clone(), account.clone());
+    rewards::settle(env, account, balance);
+
+    let mut list = escrows(env);
+    match list.first_index_of(account) {
+        Option::Some(index) if !is_escrow => {
+            list.remove(index);
+        }
+        Option::None if is_escrow => list.push_back(account.clone()),
+        _ => {}
+    }
+    env.storage().instance().set(&k_escrows(), &list);
+
+    env.events()
+        .publish((symbol_short!("escrow"),), (account.clone(), is_escrow));
+}
+
+#[contractimpl]
+impl PapexToken {
+    pub fn escrow_accounts(env: Env) -> Vec<Address> {
+        escrows(&env)
+    }
+
+    /// Registers or removes an account that holds tokens for others. Escrow
+    /// balances earn no holder rewards.
+    pub fn set_escrow_account(env: Env, caller: Address, account: Address, is_escrow: bool) {
+        Self::require_owner_call(&env, &caller);
+        set_escrow(&env, &account, is_escrow);
+    }
+}
@@ -0,0 +1,303 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, BytesN, Env, Symbol};
+
+use crate::{
+    airdrop, delisting, escrow, launch, snapshots, LaunchLimits, PapexToken, PapexTokenClient,
+};
+
+fn k_governance() -> Symbol { symbol_short!("gov") }
+fn k_next_proposal() -> Symbol { symbol_short!("nprop") }
+
+/// Proposals and vote receipts are extended to roughly 180 days of ledgers
+/// once they fall below roughly 30 days.
+const PROPOSAL_TTL_THRESHOLD: u32 = 518_400;
+const PROPOSAL_TTL_EXTEND_TO: u32 = 3_110_400;
+
+/// Persistent keys, one entry per proposal and per vote cast.
+#[derive(Clone)]
+#[contracttype]
+pub enum GovernanceKey {
+    Proposal(u32),
+    Voted(u32, Address),
+}
+
+/// Holder governance settings. Durations are in seconds and `quorum_bps` is
+/// the share of the snapshot supply that must take part in a vote.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct GovernanceParams {
+    pub voting_period: u64,
+    pub quorum_bps: u32,
+    pub timelock: u64,
+    pub proposal_threshold: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ProposalAction {
+    SetTrading(bool),
+    WithdrawLiquidity(i128, Address),
+    SetOwner(Address),
+    Snapshot,
+    SetPaused(bool),
+    SetEscrowAccount(Address, bool),
+    SetLaunchLimits(LaunchLimits),
+    StartAirdrop(BytesN<32>, i128),
+    CloseAirdrop(u32),
+    SetDelister(Option<Address>),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum ProposalStatus {
+    Active,
+    Defeated,
+    Queued,
+    Executed,
+    Cancelled,
+}
+
+/// Votes are weighted by balances held when `snapshot_ledger` opened, so
+/// tokens bought after the proposal was created carry no weight. `eta` is
+/// zero until the proposal is queued.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Proposal {
+    pub id: u32,
+    pub proposer: Address,
+    pub action: ProposalAction,
+    pub snapshot_ledger: u32,
+    pub vote_ends_at: u64,
+    pub votes_for: i128,
+    pub votes_against: i128,
+    pub eta: u64,
+    pub status: ProposalStatus,
+}
+
+fn params(env: &Env) -> Option<GovernanceParams> {
+    env.storage().instance().get(&k_governance())
+}
+
+pub(crate) fn enabled(env: &Env) -> bool {
+    env.storage().instance().has(&k_governance())
+}
+
+fn bump(env: &Env, key: &GovernanceKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, PROPOSAL_TTL_THRESHOLD, PROPOSAL_TTL_EXTEND_TO);
+}
+
+fn read_proposal(env: &Env, proposal_id: u32) -> Proposal {
+    env.storage()
+        .persistent()
+        .get(&GovernanceKey::Proposal(proposal_id))
+        .unwrap_or_else(|| panic!("proposal not found"))
+}
+
+fn write_proposal(env: &Env, proposal: &Proposal) {
+    let key = GovernanceKey::Proposal(proposal.id);
+    env.storage().persistent().set(&key, proposal);
+    bump(env, &key);
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Hands trading, liquidity, ownership, escrow, launch, airdrop and
+    /// delisting decisions to token holders. The owner setters for those are disabled
+    /// from then on; there is no way back. Proposing takes a positive
+    /// `proposal_threshold` of tokens.
+    pub fn enable_governance(env: Env, caller: Address, params: GovernanceParams) {
+        Self::require_owner_call(&env, &caller);
+        if params.voting_period == 0
+            || params.quorum_bps == 0
+            || params.quorum_bps > 10_000
+            || params.proposal_threshold <= 0
+        {
+            panic!("invalid governance params");
+        }
+        env.storage().instance().set(&k_governance(), &params);
+        env.events().publish(
+            (symbol_short!("gov"),),
+            (caller, params.voting_period, params.quorum_bps, params.timelock),
+        );
+    }
+
+    pub fn governance(env: Env) -> Option<GovernanceParams> {
+        params(&env)
+    }
+
+    pub fn get_proposal(env: Env, proposal_id: u32) -> Option<Proposal> {
+        env.storage()
+            .persistent()
+            .get(&GovernanceKey::Proposal(proposal_id))
+    }
+
+    pub fn propose(env: Env, proposer: Address, action: ProposalAction) -> u32 {
+        proposer.require_auth();
+        let params = params(&env).unwrap_or_else(|| panic!("governance disabled"));
+        let ledger = env.ledger().sequence();
+        if snapshots::balance_at(&env, &proposer, ledger) < params.proposal_threshold {
+            panic!("below proposal threshold");
+        }
+        match &action {
+            ProposalAction::WithdrawLiquidity(amount, _) => Self::ensure_amount(*amount),
+            ProposalAction::StartAirdrop(_, pool) => Self::ensure_amount(*pool),
+            _ => {}
+        }
+
+        let proposal_id: u32 = env
+            .storage()
+            .instance()
+            .get(&k_next_proposal())
+            .unwrap_or(0);
+        env.storage()
+            .instance()
+            .set(&k_next_proposal(), &proposal_id.checked_add(1).expect("id overflow"));
+
+        let proposal = Proposal {
+            id: proposal_id,
+            proposer: proposer.clone(),
+            action,
+            snapshot_ledger: ledger,
+            vote_ends_at: env.ledger().timestamp() + params.voting_period,
+            votes_for: 0,
+            votes_against: 0,
+            eta: 0,
+            status: ProposalStatus::Active,
+        };
+        write_proposal(&env, &proposal);
+
+        env.events()
+            .publish((symbol_short!("propose"),), (proposal_id, proposer, ledger));
+
+        proposal_id
+    }
+
+    pub fn vote(env: Env, voter: Address, proposal_id: u32, support: bool) -> i128 {
+        voter.require_auth();
+        let mut proposal = read_proposal(&env, proposal_id);
+        if proposal.status != ProposalStatus::Active
+            || env.ledger().timestamp() >= proposal.vote_ends_at
+        {
+            panic!("voting closed");
+        }
+
+        let receipt = GovernanceKey::Voted(proposal_id, voter.clone());
+        if env.storage().persistent().has(&receipt) {
+            panic!("already voted");
+        }
+
+        let weight = snapshots::balance_at(&env, &voter, proposal.snapshot_ledger);
+        if weight <= 0 {
+            panic!("no voting power");
+        }
+        env.storage().persistent().set(&receipt, &support);
+        bump(&env, &receipt);
+
+        if support {
+            proposal.votes_for += weight;
+        } else {
+            proposal.votes_against += weight;
+        }
+        write_proposal(&env, &proposal);
+
+        env.events()
+            .publish((symbol_short!("pvote"),), (proposal_id, voter, support, weight));
+
+        weight
+    }
+
+    /// Closes a finished vote. Proposals that met quorum with a majority in
+    /// favour are queued behind the timelock; the rest are defeated.
+    pub fn queue_proposal(env: Env, proposal_id: u32) -> Proposal {
+        let mut proposal = read_proposal(&env, proposal_id);
+        if proposal.status != ProposalStatus::Active {
+            panic!("proposal not active");
+        }
+        let now = env.ledger().timestamp();
+        if now < proposal.vote_ends_at {
+            panic!("vote not finished");
+        }
+
+        let params = params(&env).unwrap();
+        let supply = snapshots::total_supply_at(&env, proposal.snapshot_ledger);
+        let turnout = proposal.votes_for + proposal.votes_against;
+        if turnout * 10_000 >= supply * (params.quorum_bps as i128)
+            && proposal.votes_for > proposal.votes_against
+        {
+            proposal.status = ProposalStatus::Queued;
+            proposal.eta = now + params.timelock;
+        } else {
+            proposal.status = ProposalStatus::Defeated;
+        }
+        write_proposal(&env, &proposal);
+
+        env.events().publish(
+            (symbol_short!("pqueue"),),
+            (proposal_id, proposal.status as u32, proposal.eta),
+        );
+
+        proposal
+    }
+
+    /// Applies a queued proposal once its timelock has passed.
+    pub fn execute_proposal(env: Env, proposal_id: u32) -> Proposal {
+        let mut proposal = read_proposal(&env, proposal_id);
+        if proposal.status != ProposalStatus::Queued {
+            panic!("proposal not queued");
+        }
+        if env.ledger().timestamp() < proposal.eta {
+            panic!("timelock active");
+        }
+        proposal.status = ProposalStatus::Executed;
+        write_proposal(&env, &proposal);
+
+        let by = env.current_contract_address();
+        match proposal.action.clone() {
+            ProposalAction::SetTrading(is_on) => Self::apply_trading(&env, is_on),
+            ProposalAction::WithdrawLiquidity(amount, to) => {
+                Self::apply_withdraw(&env, &by, amount, &to)
+            }
+            ProposalAction::SetOwner(owner) => Self::apply_owner(&env, &owner),
+            ProposalAction::Snapshot => {
+                snapshots::take(&env, &by);
+            }
+            ProposalAction::SetPaused(paused) => Self::apply_paused(&env, &by, paused),
+            ProposalAction::SetEscrowAccount(account, is_escrow) => {
+                escrow::set_escrow(&env, &account, is_escrow)
+            }
+            ProposalAction::SetLaunchLimits(limits) => launch::set_limits(&env, &limits),
+            ProposalAction::StartAirdrop(root, pool) => {
+                airdrop::start(&env, &root, pool);
+            }
+            ProposalAction::CloseAirdrop(airdrop_id) => {
+                airdrop::close(&env, airdrop_id);
+            }
+            ProposalAction::SetDelister(delister) => delisting::set_delister(&env, &delister),
+        }
+
+        env.events()
+            .publish((symbol_short!("pexec"),), (proposal_id,));
+
+        proposal
+    }
+
+    /// Withdraws a proposal while it is still being voted on. Only the
+    /// proposer may cancel; a queued proposal has passed and belongs to the
+    /// holders.
+    pub fn cancel_proposal(env: Env, caller: Address, proposal_id: u32) {
+        caller.require_auth();
+        let mut proposal = read_proposal(&env, proposal_id);
+        if caller != proposal.proposer {
+            panic!("only proposer");
+        }
+        if proposal.status != ProposalStatus::Active {
+            panic!("proposal closed");
+        }
+        proposal.status = ProposalStatus::Cancelled;
+        write_proposal(&env, &proposal);
+
+        env.events()
+            .publish((symbol_short!("pcancel"),), (proposal_id, caller));
+    }
+}
@@ -0,0 +1,143 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Symbol};
+
+use crate::{escrow, Config, PapexToken, PapexTokenClient};
+
+fn k_launched_at() -> Symbol { symbol_short!("lch") }
+
+/// Ledgers are closed roughly every five seconds; cooldown entries are kept
+/// at most about 180 days.
+const SECONDS_PER_LEDGER: u64 = 5;
+const MAX_LAST_BUY_TTL: u64 = 3_110_400;
+
+/// Persistent per-buyer key holding the time of the last launch-window buy.
+#[derive(Clone)]
+#[contracttype]
+pub enum LaunchKey {
+    LastBuy(Address),
+}
+
+/// Limits applied for `window` seconds after trading is first enabled. A
+/// zero value disables that limit, and a zero window disables them all.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[contracttype]
+pub struct LaunchLimits {
+    pub max_wallet: i128,
+    pub max_tx: i128,
+    pub cooldown: u64,
+    pub window: u64,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct LaunchStatus {
+    pub limits: LaunchLimits,
+    pub started_at: u64,
+    pub ends_at: u64,
+    pub active: bool,
+}
+
+fn launched_at(env: &Env) -> Option<u64> {
+    env.storage().instance().get(&k_launched_at())
+}
+
+fn status(env: &Env, cfg: &Config) -> LaunchStatus {
+    let started_at = launched_at(env).unwrap_or(0);
+    let ends_at = if started_at > 0 { started_at + cfg.launch.window } else { 0 };
+    LaunchStatus {
+        limits: cfg.launch.clone(),
+        started_at,
+        ends_at,
+        active: launched_at(env).is_some() && env.ledger().timestamp() < ends_at,
+    }
+}
+
+/// Starts the launch window the first time trading is switched on.
+pub(crate) fn on_trading_enabled(env: &Env) {
+    if launched_at(env).is_none() {
+        env.storage()
+            .instance()
+            .set(&k_launched_at(), &env.ledger().timestamp());
+    }
+}
+
+pub(crate) fn started(env: &Env) -> bool {
+    launched_at(env).is_some()
+}
+
+pub(crate) fn active(env: &Env, cfg: &Config) -> bool {
+    status(env, cfg).active
+}
+
+/// Holding cap for any address receiving tokens during the window. Escrow
+/// accounts hold tokens for others and are exempt.
+pub(crate) fn check_wallet(env: &Env, cfg: &Config, to: &Address, balance_after: i128) {
+    if cfg.launch.max_wallet > 0
+        && active(env, cfg)
+        && balance_after > cfg.launch.max_wallet
+        && !escrow::is_escrow(env, to)
+    {
+        panic!("exceeds max per wallet");
+    }
+}
+
+pub(crate) fn check_buy(
+    env: &Env,
+    cfg: &Config,
+    buyer: &Address,
+    amount: i128,
+    balance_after: i128,
+) {
+    if !active(env, cfg) {
+        return;
+    }
+    if cfg.launch.max_tx > 0 && amount > cfg.launch.max_tx {
+        panic!("exceeds max per tx");
+    }
+    check_wallet(env, cfg, buyer, balance_after);
+    if cfg.launch.cooldown > 0 {
+        let now = env.ledger().timestamp();
+        let key = LaunchKey::LastBuy(buyer.clone());
+        let last: Option<u64> = env.storage().persistent().get(&key);
+        if let Option::Some(last) = last {
+            if now < last + cfg.launch.cooldown {
+                panic!("buy cooldown");
+            }
+        }
+        // The entry only matters until the window closes.
+        let ledgers = (status(env, cfg).ends_at - now) / SECONDS_PER_LEDGER + 1;
+        let ttl = ledgers.min(MAX_LAST_BUY_TTL) as u32;
+        env.storage().persistent().set(&key, &now);
+        env.storage().persistent().extend_ttl(&key, ttl, ttl);
+    }
+}
+
+/// Applies new launch limits, for the owner or an executed proposal.
+pub(crate) fn set_limits(env: &Env, limits: &LaunchLimits) {
+    if launched_at(env).is_some() {
+        panic!("already launched");
+    }
+    if limits.max_wallet < 0 || limits.max_tx < 0 {
+        panic!("invalid launch limits");
+    }
+    let mut cfg = PapexToken::read_cfg(env);
+    cfg.launch = limits.clone();
+    PapexToken::write_cfg(env, &cfg);
+    env.events().publish(
+        (symbol_short!("launch"),),
+        (limits.max_wallet, limits.max_tx, limits.cooldown, limits.window),
+    );
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Sets the launch limits. Only possible before trading is first enabled.
+    pub fn set_launch_limits(env: Env, caller: Address, limits: LaunchLimits) {
+        Self::require_owner_call(&env, &caller);
+        set_limits(&env, &limits);
+    }
+
+    /// Configured launch limits and whether they currently apply.
+    pub fn launch_limits(env: Env) -> LaunchStatus {
+        status(&env, &Self::read_cfg(&env))
+    }
+}
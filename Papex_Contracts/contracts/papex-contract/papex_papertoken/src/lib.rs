@@ -1,6 +1,7 @@
 #![no_std]
 
 use core::option::Option;
+use papex_common::pause;
 use soroban_sdk::{
     contract, contractimpl, contracttype, symbol_short, Address, Env, Map, String, Symbol,
 };
@@ -11,6 +12,28 @@ fn k_bal() -> Symbol { symbol_short!("bal") }
 fn k_tot() -> Symbol { symbol_short!("tot") }
 fn k_lq() -> Symbol { symbol_short!("lqd") }
 
+mod airdrop;
+mod delisting;
+mod escrow;
+mod governance;
+mod launch;
+mod merkle;
+mod oracle;
+mod orders;
+mod presale;
+mod rewards;
+mod snapshots;
+pub use airdrop::Airdrop;
+pub use delisting::{DelistPhase, Delisting};
+pub use governance::{GovernanceParams, Proposal, ProposalAction, ProposalStatus};
+pub use launch::{LaunchLimits, LaunchStatus};
+pub use oracle::Observation;
+pub use orders::{Order, OrderSide};
+pub use presale::{Presale, PresaleAccess, PresaleTerms};
+pub use rewards::RewardState;
+pub use snapshots::Checkpoint;
+pub use papex_common::{PauseController, PauseControllerClient};
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Config {
@@ -22,6 +45,7 @@ pub struct Config {
     pub slope: i128,
     pub payment_token: Option<Address>,
     pub trading: bool,
+    pub launch: LaunchLimits,
 }
 
 #[derive(Clone)]
@@ -73,12 +97,32 @@ impl PapexToken {
         env.storage().instance().set(&k_bal(), balances);
     }
 
+    /// Updates `who`'s balance in `balances`, settling holder rewards on the
+    /// old balance first and checkpointing the new one. Callers still persist
+    /// the map.
+    fn set_balance(env: &Env, balances: &mut Map<Address, i128>, who: &Address, balance: i128) {
+        let previous = balances.get(who.clone()).unwrap_or(0);
+        rewards::settle(env, who, previous);
+        balances.set(who.clone(), balance);
+        snapshots::record_balance(env, who, balance);
+    }
+
     fn read_total_supply(env: &Env) -> i128 {
         env.storage().instance().get(&k_tot()).unwrap_or(0)
     }
 
     fn write_total_supply(env: &Env, amount: i128) {
         env.storage().instance().set(&k_tot(), &amount);
+        snapshots::record_supply(env, amount);
+        let cfg = Self::read_cfg(env);
+        oracle::record(env, cfg.base_price + cfg.slope * Self::curve_supply(env));
+    }
+
+    /// Supply the curve is priced at: everything minted except claimed
+    /// airdrops, which never paid into liquidity. Pricing sells at the total
+    /// would pay out more than buyers put in.
+    fn curve_supply(env: &Env) -> i128 {
+        Self::read_total_supply(env) - airdrop::unbacked(env)
     }
 
     fn read_liquidity(env: &Env) -> i128 {
@@ -89,6 +133,81 @@ impl PapexToken {
         env.storage().instance().set(&k_lq(), &amount);
     }
 
+    fn require_owner(env: &Env, caller: &Address) -> Config {
+        let cfg = Self::read_cfg(env);
+        caller.require_auth();
+        if *caller != cfg.owner {
+            panic!("only owner");
+        }
+        cfg
+    }
+
+    /// Owner check for settings that move to holders once governance is on.
+    fn require_owner_call(env: &Env, caller: &Address) -> Config {
+        let cfg = Self::require_owner(env, caller);
+        if governance::enabled(env) {
+            panic!("governed by holders");
+        }
+        cfg
+    }
+
+    fn apply_trading(env: &Env, is_on: bool) {
+        if is_on && presale::pending(env) {
+            panic!("presale active");
+        }
+        let mut cfg = Self::read_cfg(env);
+        cfg.trading = is_on;
+        Self::write_cfg(env, &cfg);
+        if is_on {
+            launch::on_trading_enabled(env);
+        }
+        env.events().publish((symbol_short!("trade"),), (is_on,));
+    }
+
+    fn apply_withdraw(env: &Env, by: &Address, amount: i128, to: &Address) {
+        if delisting::reserve_locked(env) {
+            panic!("reserve locked for redemption");
+        }
+        let cfg = Self::read_cfg(env);
+        let liquidity = Self::read_liquidity(env);
+        if liquidity < amount {
+            panic!("insufficient liquidity");
+        }
+        let new_liquidity = liquidity - amount;
+        Self::write_liquidity(env, new_liquidity);
+
+        if let Option::Some(token) = cfg.payment_token.clone() {
+            let client = Self::token_client(env, &token);
+            client.transfer(&env.current_contract_address(), to, &amount);
+        }
+
+        env.events().publish(
+            (symbol_short!("lqout"),),
+            (by.clone(), to.clone(), amount, new_liquidity),
+        );
+    }
+
+    fn apply_paused(env: &Env, by: &Address, paused: bool) {
+        pause::set_paused(env, paused);
+        let topic = if paused { symbol_short!("pause") } else { symbol_short!("unpause") };
+        env.events().publish((topic,), (by.clone(),));
+    }
+
+    fn apply_owner(env: &Env, owner: &Address) {
+        let mut cfg = Self::read_cfg(env);
+        let previous = cfg.owner.clone();
+        cfg.owner = owner.clone();
+        Self::write_cfg(env, &cfg);
+        env.events()
+            .publish((symbol_short!("owner"),), (previous, owner.clone()));
+    }
+
+    fn ensure_not_paused(env: &Env) {
+        if pause::paused(env) {
+            panic!("paused");
+        }
+    }
+
     fn ensure_amount(amount: i128) {
         if amount <= 0 {
             panic!("amount must be positive");
@@ -154,6 +273,7 @@ impl PapexToken {
             slope,
             payment_token: payment_token.clone(),
             trading: false,
+            launch: LaunchLimits::default(),
         };
 
         env.storage().instance().set(&k_cfg(), &cfg);
@@ -161,7 +281,7 @@ impl PapexToken {
 
         let mut balances = Map::<Address, i128>::new(&env);
         if initial_supply_to_owner > 0 {
-            balances.set(owner.clone(), initial_supply_to_owner);
+            Self::set_balance(&env, &mut balances, &owner, initial_supply_to_owner);
         }
         Self::write_balances(&env, &balances);
 
@@ -206,13 +326,54 @@ impl PapexToken {
     }
 
     pub fn set_trading(env: Env, caller: Address, is_on: bool) {
-        let mut cfg = Self::read_cfg(&env);
-        if caller != cfg.owner {
-            panic!("only owner");
+        Self::require_owner_call(&env, &caller);
+        Self::apply_trading(&env, is_on);
+    }
+
+    pub fn set_owner(env: Env, caller: Address, new_owner: Address) {
+        Self::require_owner_call(&env, &caller);
+        Self::apply_owner(&env, &new_owner);
+    }
+
+    /// True when this token or its pause controller is paused.
+    pub fn is_paused(env: Env) -> bool {
+        pause::paused(&env)
+    }
+
+    pub fn pauser(env: Env) -> Option<Address> {
+        pause::pauser(&env)
+    }
+
+    pub fn pause_controller(env: Env) -> Option<Address> {
+        pause::controller(&env)
+    }
+
+    pub fn set_pauser(env: Env, caller: Address, pauser: Option<Address>) {
+        Self::require_owner_call(&env, &caller);
+        pause::set_pauser(&env, &caller, pauser);
+    }
+
+    /// Points the token at a shared pause controller (for example the
+    /// registry). The controller must not consult this token in turn.
+    pub fn set_pause_controller(env: Env, caller: Address, controller: Option<Address>) {
+        Self::require_owner_call(&env, &caller);
+        pause::set_controller(&env, &caller, controller);
+    }
+
+    /// Halts `buy`, `sell` and `transfer`. Callable by the pauser, or by the
+    /// owner until governance is enabled.
+    pub fn pause(env: Env, caller: Address) {
+        if pause::pauser(&env) == Option::Some(caller.clone()) {
+            caller.require_auth();
+        } else {
+            Self::require_owner_call(&env, &caller);
         }
-        cfg.trading = is_on;
-        Self::write_cfg(&env, &cfg);
-        env.events().publish((symbol_short!("trade"),), (is_on,));
+        Self::apply_paused(&env, &caller, true);
+    }
+
+    pub fn unpause(env: Env, caller: Address) {
+        Self::require_owner_call(&env, &caller);
+        Self::apply_paused(&env, &caller, false);
     }
 
     pub fn total_supply(env: Env) -> i128 {
@@ -226,37 +387,62 @@ impl PapexToken {
 
     pub fn current_price(env: Env) -> i128 {
         let cfg = Self::read_cfg(&env);
-        let supply = Self::read_total_supply(&env);
-        cfg.base_price + cfg.slope * supply
+        cfg.base_price + cfg.slope * Self::curve_supply(&env)
     }
 
     pub fn quote_buy(env: Env, amount: i128) -> Quote {
         Self::ensure_amount(amount);
         let cfg = Self::read_cfg(&env);
-        let supply = Self::read_total_supply(&env);
+        let supply = Self::curve_supply(&env);
         Self::bonding_quote(cfg.base_price, cfg.slope, supply, amount, true)
     }
 
+    /// Largest amount `payment` buys on the curve right now, capped by the
+    /// supply still for sale.
+    pub fn quote_buy_for_payment(env: Env, payment: i128) -> i128 {
+        let cfg = Self::read_cfg(&env);
+        let supply = Self::read_total_supply(&env);
+        let curve_supply = Self::curve_supply(&env);
+        let mut lo = 0;
+        let mut hi = (cfg.max_supply - airdrop::reserved(&env) - supply).max(0);
+        while lo < hi {
+            let mid = lo + (hi - lo + 1) / 2;
+            let quote = Self::bonding_quote(cfg.base_price, cfg.slope, curve_supply, mid, true);
+            if quote.cost <= payment {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        lo
+    }
+
+    pub fn payment_token(env: Env) -> Option<Address> {
+        Self::read_cfg(&env).payment_token
+    }
+
     pub fn quote_sell(env: Env, amount: i128) -> Quote {
         Self::ensure_amount(amount);
         let cfg = Self::read_cfg(&env);
-        let supply = Self::read_total_supply(&env);
+        let supply = Self::curve_supply(&env);
         Self::bonding_quote(cfg.base_price, cfg.slope, supply, amount, false)
     }
 
     pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
         Self::ensure_amount(amount);
         from.require_auth();
+        Self::ensure_not_paused(&env);
 
         let mut balances = Self::read_balances(&env);
         let from_balance = balances.get(from.clone()).unwrap_or(0);
         if from_balance < amount {
             panic!("insufficient balance");
         }
-        balances.set(from.clone(), from_balance - amount);
+        Self::set_balance(&env, &mut balances, &from, from_balance - amount);
 
         let to_balance = balances.get(to.clone()).unwrap_or(0);
-        balances.set(to.clone(), to_balance + amount);
+        launch::check_wallet(&env, &Self::read_cfg(&env), &to, to_balance + amount);
+        Self::set_balance(&env, &mut balances, &to, to_balance + amount);
 
         Self::write_balances(&env, &balances);
         env.events().publish((symbol_short!("transfer"),), (from, to, amount));
@@ -285,33 +471,16 @@ impl PapexToken {
 
     pub fn withdraw_liquidity(env: Env, caller: Address, amount: i128, to: Address) {
         Self::ensure_amount(amount);
-        let cfg = Self::read_cfg(&env);
-        caller.require_auth();
-        if caller != cfg.owner {
-            panic!("only owner");
-        }
-
-        let liquidity = Self::read_liquidity(&env);
-        if liquidity < amount {
-            panic!("insufficient liquidity");
-        }
-        let new_liquidity = liquidity - amount;
-        Self::write_liquidity(&env, new_liquidity);
-
-        if let Option::Some(token) = cfg.payment_token.clone() {
-            let client = Self::token_client(&env, &token);
-            client.transfer(&env.current_contract_address(), &to, &amount);
-        }
-
-        env.events().publish(
-            (symbol_short!("lqout"),),
-            (caller, to, amount, new_liquidity),
-        );
+        Self::require_owner_call(&env, &caller);
+        Self::apply_withdraw(&env, &caller, amount, &to);
     }
 
     pub fn buy(env: Env, buyer: Address, amount: i128, max_payment: i128) -> Quote {
         Self::ensure_amount(amount);
         buyer.require_auth();
+        Self::ensure_not_paused(&env);
+        presale::settle(&env);
+        delisting::ensure_listed(&env);
 
         let cfg = Self::read_cfg(&env);
         if !cfg.trading {
@@ -319,14 +488,17 @@ impl PapexToken {
         }
 
         let mut supply = Self::read_total_supply(&env);
-        if supply + amount > cfg.max_supply {
+        if supply + amount > cfg.max_supply - airdrop::reserved(&env) {
             panic!("exceeds max supply");
         }
 
-        let quote = Self::bonding_quote(cfg.base_price, cfg.slope, supply, amount, true);
+        let curve_supply = Self::curve_supply(&env);
+        let quote = Self::bonding_quote(cfg.base_price, cfg.slope, curve_supply, amount, true);
         if max_payment < quote.cost {
             panic!("insufficient payment");
         }
+        let held = Self::balance_of(env.clone(), buyer.clone());
+        launch::check_buy(&env, &cfg, &buyer, amount, held + amount);
 
         if let Option::Some(token) = cfg.payment_token.clone() {
             let client = Self::token_client(&env, &token);
@@ -343,7 +515,7 @@ impl PapexToken {
 
         let mut balances = Self::read_balances(&env);
         let current = balances.get(buyer.clone()).unwrap_or(0);
-        balances.set(buyer.clone(), current + amount);
+        Self::set_balance(&env, &mut balances, &buyer, current + amount);
         Self::write_balances(&env, &balances);
 
         let liquidity = Self::read_liquidity(&env) + quote.cost;
@@ -354,12 +526,16 @@ impl PapexToken {
             (buyer.clone(), amount, quote.cost, liquidity),
         );
 
+        orders::match_orders(&env, OrderSide::Sell);
         quote
     }
 
     pub fn sell(env: Env, seller: Address, amount: i128, min_payment: i128) -> Quote {
         Self::ensure_amount(amount);
         seller.require_auth();
+        Self::ensure_not_paused(&env);
+        presale::settle(&env);
+        delisting::ensure_sellable(&env);
 
         let cfg = Self::read_cfg(&env);
         if !cfg.trading {
@@ -373,7 +549,8 @@ impl PapexToken {
         }
 
         let supply = Self::read_total_supply(&env);
-        let quote = Self::bonding_quote(cfg.base_price, cfg.slope, supply, amount, false);
+        let curve_supply = Self::curve_supply(&env);
+        let quote = Self::bonding_quote(cfg.base_price, cfg.slope, curve_supply, amount, false);
         if quote.cost < min_payment {
             panic!("slippage");
         }
@@ -385,7 +562,7 @@ impl PapexToken {
         let new_liquidity = liquidity - quote.cost;
         Self::write_liquidity(&env, new_liquidity);
 
-        balances.set(seller.clone(), current - amount);
+        Self::set_balance(&env, &mut balances, &seller, current - amount);
         Self::write_balances(&env, &balances);
 
         let new_supply = supply - amount;
@@ -401,6 +578,7 @@ impl PapexToken {
             (seller.clone(), amount, quote.cost, new_liquidity),
         );
 
+        orders::match_orders(&env, OrderSide::Buy);
         quote
     }
 }
@@ -0,0 +1,23 @@
+use soroban_sdk::{Bytes, BytesN, Env, Vec};
+
+/// Checks a Merkle proof where each parent is `sha256(min(a, b) || max(a, b))`,
+/// so proofs carry no left/right flags.
+pub(crate) fn verify(
+    env: &Env,
+    root: &BytesN<32>,
+    leaf: BytesN<32>,
+    proof: &Vec<BytesN<32>>,
+) -> bool {
+    let mut node = leaf;
+    for sibling in proof.iter() {
+        let (a, b) = if node.to_array() <= sibling.to_array() {
+            (node, sibling)
+        } else {
+            (sibling, node)
+        };
+        let mut preimage = Bytes::from_array(env, &a.to_array());
+        preimage.append(&Bytes::from_array(env, &b.to_array()));
+        node = env.crypto().sha256(&preimage).to_bytes();
+    }
+    node == *root
+}
@@ -0,0 +1,117 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Env, Symbol, Vec};
+
+use crate::{PapexToken, PapexTokenClient};
+
+fn k_observations() -> Symbol { symbol_short!("obs") }
+fn k_next_observation() -> Symbol { symbol_short!("obsi") }
+
+/// Number of observations kept in the ring buffer.
+const OBSERVATION_CAPACITY: u32 = 64;
+
+/// Curve price from `timestamp` on, and the time-weighted sum of all prices
+/// up to `timestamp`.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Observation {
+    pub timestamp: u64,
+    pub price: i128,
+    pub cumulative: i128,
+}
+
+fn observations(env: &Env) -> Vec<Observation> {
+    env.storage()
+        .instance()
+        .get(&k_observations())
+        .unwrap_or(Vec::new(env))
+}
+
+fn next_slot(env: &Env) -> u32 {
+    env.storage().instance().get(&k_next_observation()).unwrap_or(0)
+}
+
+fn newest_index(ring: &Vec<Observation>, next: u32) -> u32 {
+    (next + ring.len() - 1) % ring.len()
+}
+
+/// Records the price in effect from now on. A price changed several times
+/// within one timestamp only keeps the last value, and it accrues weight only
+/// as time passes, so moving the price inside one transaction does not shift
+/// the average.
+pub(crate) fn record(env: &Env, price: i128) {
+    let now = env.ledger().timestamp();
+    let mut ring = observations(env);
+    let mut next = next_slot(env);
+
+    if ring.is_empty() {
+        ring.push_back(Observation { timestamp: now, price, cumulative: 0 });
+        next = 1;
+    } else {
+        let index = newest_index(&ring, next);
+        let mut last = ring.get(index).unwrap();
+        if last.timestamp == now {
+            last.price = price;
+            ring.set(index, last);
+        } else {
+            let entry = Observation {
+                timestamp: now,
+                price,
+                cumulative: last.cumulative + last.price * (now - last.timestamp) as i128,
+            };
+            if ring.len() < OBSERVATION_CAPACITY {
+                ring.push_back(entry);
+            } else {
+                ring.set(next, entry);
+            }
+            next = (next + 1) % OBSERVATION_CAPACITY;
+        }
+    }
+
+    env.storage().instance().set(&k_observations(), &ring);
+    env.storage().instance().set(&k_next_observation(), &next);
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Time-weighted average curve price over the last `window_seconds`.
+    /// Fails if the oldest retained observation is newer than the window start.
+    pub fn twap(env: Env, window_seconds: u64) -> i128 {
+        if window_seconds == 0 {
+            panic!("invalid window");
+        }
+        let now = env.ledger().timestamp();
+        let target = now
+            .checked_sub(window_seconds)
+            .unwrap_or_else(|| panic!("window too long"));
+        let ring = observations(&env);
+        if ring.is_empty() {
+            panic!("no observations");
+        }
+
+        let newest = newest_index(&ring, next_slot(&env));
+        let latest = ring.get(newest).unwrap();
+        let cumulative_now = latest.cumulative + latest.price * (now - latest.timestamp) as i128;
+
+        for k in 0..ring.len() {
+            let obs = ring.get((newest + ring.len() - k) % ring.len()).unwrap();
+            if obs.timestamp <= target {
+                let cumulative_then = obs.cumulative + obs.price * (target - obs.timestamp) as i128;
+                return (cumulative_now - cumulative_then) / window_seconds as i128;
+            }
+        }
+        panic!("window too long")
+    }
+
+    /// Up to `n` most recent observations, newest first.
+    pub fn observations(env: Env, n: u32) -> Vec<Observation> {
+        let ring = observations(&env);
+        let mut out = Vec::new(&env);
+        if ring.is_empty() {
+            return out;
+        }
+        let newest = newest_index(&ring, next_slot(&env));
+        for k in 0..n.min(ring.len()) {
+            out.push_back(ring.get((newest + ring.len() - k) % ring.len()).unwrap());
+        }
+        out
+    }
+}
@@ -0,0 +1,390 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Map, Symbol, Vec};
+
+use crate::{
+    airdrop, delisting, launch, presale, Config, DelistPhase, PapexToken, PapexTokenClient,
+};
+
+fn k_orders() -> Symbol { symbol_short!("ord") }
+fn k_next_order() -> Symbol { symbol_short!("nord") }
+
+/// Upper bound on resting orders, which keeps matching after each trade
+/// cheap. Expired orders are evicted before orders are counted.
+const MAX_OPEN_ORDERS: u32 = 50;
+/// Orders one owner may keep on the book.
+const MAX_ORDERS_PER_OWNER: u32 = 5;
+/// Unclaimed proceeds are extended to roughly 180 days of ledgers once they
+/// fall below roughly 30 days.
+const PROCEEDS_TTL_THRESHOLD: u32 = 518_400;
+const PROCEEDS_TTL_EXTEND_TO: u32 = 3_110_400;
+
+/// Persistent keys for payment-asset amounts owed to order owners.
+#[derive(Clone)]
+#[contracttype]
+pub enum OrderKey {
+    Proceeds(Address),
+}
+/// Smallest order notional, as a multiple of the curve's base price.
+const MIN_ORDER_NOTIONAL_BASES: i128 = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Resting limit order against the curve. Buy orders escrow `price * amount`
+/// of the payment asset and sell orders escrow the tokens, both held by this
+/// contract. `expires_at == 0` means the order does not expire.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Order {
+    pub id: u32,
+    pub owner: Address,
+    pub side: OrderSide,
+    pub price: i128,
+    pub amount: i128,
+    pub remaining: i128,
+    pub escrow: i128,
+    pub expires_at: u64,
+}
+
+fn orders(env: &Env) -> Map<u32, Order> {
+    env.storage()
+        .instance()
+        .get(&k_orders())
+        .unwrap_or(Map::new(env))
+}
+
+fn write_orders(env: &Env, orders: &Map<u32, Order>) {
+    env.storage().instance().set(&k_orders(), orders);
+}
+
+fn expired(env: &Env, order: &Order) -> bool {
+    order.expires_at != 0 && env.ledger().timestamp() >= order.expires_at
+}
+
+/// Largest fill of `order` against the curve whose average price is no
+/// worse than the order's limit.
+fn fillable(env: &Env, cfg: &Config, order: &Order) -> i128 {
+    let supply = PapexToken::curve_supply(env);
+    let mut hi = match order.side {
+        OrderSide::Buy => order
+            .remaining
+            .min(cfg.max_supply - airdrop::reserved(env) - PapexToken::read_total_supply(env)),
+        OrderSide::Sell => order.remaining.min(supply),
+    };
+    let liquidity = PapexToken::read_liquidity(env);
+    let mut lo = 0;
+    while lo < hi {
+        let mid = (lo + hi + 1) / 2;
+        let is_buy = order.side == OrderSide::Buy;
+        let quote = PapexToken::bonding_quote(cfg.base_price, cfg.slope, supply, mid, is_buy);
+        let ok = if is_buy {
+            quote.cost <= order.price * mid
+        } else {
+            quote.cost >= order.price * mid && quote.cost <= liquidity
+        };
+        if ok {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    lo
+}
+
+/// Best-priced live order on `side`: lowest ask or highest bid, oldest first.
+fn best_order(env: &Env, book: &Map<u32, Order>, side: OrderSide) -> Option<Order> {
+    let mut best: Option<Order> = Option::None;
+    for (_, order) in book.iter() {
+        if order.side != side || expired(env, &order) {
+            continue;
+        }
+        let better = match &best {
+            Option::None => true,
+            Option::Some(b) => match side {
+                OrderSide::Sell => order.price < b.price,
+                OrderSide::Buy => order.price > b.price,
+            },
+        };
+        if better {
+            best = Option::Some(order);
+        }
+    }
+    best
+}
+
+fn proceeds(env: &Env, owner: &Address) -> i128 {
+    env.storage()
+        .persistent()
+        .get(&OrderKey::Proceeds(owner.clone()))
+        .unwrap_or(0)
+}
+
+/// Owes `amount` of the payment asset to `owner`, to be taken with
+/// `claim_proceeds`. Fills never transfer to owners directly, so an owner who
+/// cannot receive the asset cannot block trading.
+fn credit(env: &Env, owner: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    let key = OrderKey::Proceeds(owner.clone());
+    env.storage()
+        .persistent()
+        .set(&key, &(proceeds(env, owner) + amount));
+    env.storage()
+        .persistent()
+        .extend_ttl(&key, PROCEEDS_TTL_THRESHOLD, PROCEEDS_TTL_EXTEND_TO);
+}
+
+/// Drops expired orders, crediting their payment escrow and handing their
+/// tokens back.
+fn evict_expired(env: &Env, book: &mut Map<u32, Order>) {
+    let this = env.current_contract_address();
+    for (id, order) in book.clone().iter() {
+        if !expired(env, &order) {
+            continue;
+        }
+        book.remove(id);
+        match order.side {
+            OrderSide::Buy => credit(env, &order.owner, order.escrow),
+            OrderSide::Sell => move_tokens(env, &this, &order.owner, order.remaining),
+        }
+        env.events()
+            .publish((symbol_short!("oexpire"),), (id, order.remaining));
+    }
+}
+
+fn pay(env: &Env, cfg: &Config, to: &Address, amount: i128) {
+    if amount <= 0 {
+        return;
+    }
+    if let Option::Some(token) = cfg.payment_token.clone() {
+        PapexToken::token_client(env, &token).transfer(
+            &env.current_contract_address(),
+            to,
+            &amount,
+        );
+    }
+}
+
+/// Moves `amount` tokens between a holder and the contract's escrow balance.
+fn move_tokens(env: &Env, from: &Address, to: &Address, amount: i128) {
+    let mut balances = PapexToken::read_balances(env);
+    let from_balance = balances.get(from.clone()).unwrap_or(0);
+    if from_balance < amount {
+        panic!("insufficient balance");
+    }
+    PapexToken::set_balance(env, &mut balances, from, from_balance - amount);
+    let to_balance = balances.get(to.clone()).unwrap_or(0);
+    PapexToken::set_balance(env, &mut balances, to, to_balance + amount);
+    PapexToken::write_balances(env, &balances);
+}
+
+fn fill(env: &Env, cfg: &Config, order: &mut Order, amount: i128) {
+    let this = env.current_contract_address();
+    let supply = PapexToken::read_total_supply(env);
+    let is_buy = order.side == OrderSide::Buy;
+    let curve_supply = PapexToken::curve_supply(env);
+    let quote = PapexToken::bonding_quote(cfg.base_price, cfg.slope, curve_supply, amount, is_buy);
+    let mut balances = PapexToken::read_balances(env);
+    let liquidity = PapexToken::read_liquidity(env);
+
+    if is_buy {
+        let held = balances.get(order.owner.clone()).unwrap_or(0);
+        PapexToken::set_balance(env, &mut balances, &order.owner, held + amount);
+        PapexToken::write_balances(env, &balances);
+        PapexToken::write_total_supply(env, supply + amount);
+        PapexToken::write_liquidity(env, liquidity + quote.cost);
+        order.escrow -= quote.cost;
+    } else {
+        let held = balances.get(this.clone()).unwrap_or(0);
+        PapexToken::set_balance(env, &mut balances, &this, held - amount);
+        PapexToken::write_balances(env, &balances);
+        PapexToken::write_total_supply(env, supply - amount);
+        PapexToken::write_liquidity(env, liquidity - quote.cost);
+        credit(env, &order.owner, quote.cost);
+    }
+    order.remaining -= amount;
+
+    env.events()
+        .publish((symbol_short!("ofill"),), (order.id, amount, quote.cost));
+}
+
+/// Fills resting orders on `side` that the last trade made executable. Runs
+/// after `buy` (asks) and `sell` (bids); fills do not cascade to the other side.
+pub(crate) fn match_orders(env: &Env, side: OrderSide) {
+    // Resting bids stop buying once a delisting starts.
+    if side == OrderSide::Buy && delisting::phase(env) != DelistPhase::Listed {
+        return;
+    }
+    let cfg = PapexToken::read_cfg(env);
+    let mut book = orders(env);
+    evict_expired(env, &mut book);
+    while let Option::Some(mut order) = best_order(env, &book, side) {
+        let amount = fillable(env, &cfg, &order);
+        if amount == 0 {
+            break;
+        }
+        fill(env, &cfg, &mut order, amount);
+        if order.remaining == 0 {
+            credit(env, &order.owner, order.escrow);
+            book.remove(order.id);
+        } else {
+            book.set(order.id, order);
+        }
+    }
+    write_orders(env, &book);
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Rests a limit order on the curve. Orders that could execute right away
+    /// are rejected; use `buy` or `sell` for those.
+    pub fn place_order(
+        env: Env,
+        owner: Address,
+        side: OrderSide,
+        price: i128,
+        amount: i128,
+        expires_at: u64,
+    ) -> u32 {
+        Self::ensure_amount(amount);
+        Self::ensure_amount(price);
+        owner.require_auth();
+        Self::ensure_not_paused(&env);
+        presale::settle(&env);
+        delisting::ensure_listed(&env);
+
+        let cfg = Self::read_cfg(&env);
+        if !cfg.trading {
+            panic!("trading disabled");
+        }
+        if launch::active(&env, &cfg) {
+            panic!("launch window active");
+        }
+        if expires_at != 0 && expires_at <= env.ledger().timestamp() {
+            panic!("invalid expiry");
+        }
+        if price * amount < cfg.base_price * MIN_ORDER_NOTIONAL_BASES {
+            panic!("order too small");
+        }
+        let mut book = orders(&env);
+        evict_expired(&env, &mut book);
+        let owned = book.values().iter().filter(|o| o.owner == owner).count() as u32;
+        if book.len() >= MAX_OPEN_ORDERS {
+            panic!("order book full");
+        }
+        if owned >= MAX_ORDERS_PER_OWNER {
+            panic!("too many orders");
+        }
+
+        let order_id: u32 = env.storage().instance().get(&k_next_order()).unwrap_or(0);
+        let order = Order {
+            id: order_id,
+            owner: owner.clone(),
+            side,
+            price,
+            amount,
+            remaining: amount,
+            escrow: if side == OrderSide::Buy { price * amount } else { 0 },
+            expires_at,
+        };
+        if fillable(&env, &cfg, &order) > 0 {
+            panic!("order is marketable");
+        }
+
+        match side {
+            OrderSide::Buy => {
+                if let Option::Some(token) = cfg.payment_token.clone() {
+                    Self::token_client(&env, &token).transfer(
+                        &owner,
+                        &env.current_contract_address(),
+                        &order.escrow,
+                    );
+                }
+            }
+            OrderSide::Sell => move_tokens(&env, &owner, &env.current_contract_address(), amount),
+        }
+
+        env.storage()
+            .instance()
+            .set(&k_next_order(), &order_id.checked_add(1).expect("id overflow"));
+        book.set(order_id, order);
+        write_orders(&env, &book);
+
+        env.events()
+            .publish((symbol_short!("order"),), (order_id, owner, side as u32, price, amount));
+
+        order_id
+    }
+
+    /// Cancels an open or expired order and returns its remaining escrow.
+    pub fn cancel_order(env: Env, caller: Address, order_id: u32) {
+        caller.require_auth();
+        let mut book = orders(&env);
+        let order = book
+            .get(order_id)
+            .unwrap_or_else(|| panic!("order not found"));
+        if caller != order.owner {
+            panic!("only order owner");
+        }
+        book.remove(order_id);
+        write_orders(&env, &book);
+
+        match order.side {
+            OrderSide::Buy => pay(&env, &Self::read_cfg(&env), &order.owner, order.escrow),
+            OrderSide::Sell => move_tokens(
+                &env,
+                &env.current_contract_address(),
+                &order.owner,
+                order.remaining,
+            ),
+        }
+
+        env.events()
+            .publish((symbol_short!("ocancel"),), (order_id, order.remaining));
+    }
+
+    /// Payment asset owed to `owner` from filled and expired orders.
+    pub fn proceeds_of(env: Env, owner: Address) -> i128 {
+        proceeds(&env, &owner)
+    }
+
+    pub fn claim_proceeds(env: Env, owner: Address) -> i128 {
+        owner.require_auth();
+        let amount = proceeds(&env, &owner);
+        if amount <= 0 {
+            panic!("nothing to claim");
+        }
+        env.storage()
+            .persistent()
+            .remove(&OrderKey::Proceeds(owner.clone()));
+        pay(&env, &Self::read_cfg(&env), &owner, amount);
+
+        env.events()
+            .publish((symbol_short!("oclaim"),), (owner, amount));
+
+        amount
+    }
+
+    pub fn get_order(env: Env, order_id: u32) -> Option<Order> {
+        orders(&env).get(order_id)
+    }
+
+    /// Resting orders, including expired ones not evicted yet, oldest first.
+    pub fn orders(env: Env) -> Vec<Order> {
+        orders(&env).values()
+    }
+
+    pub fn orders_of(env: Env, owner: Address) -> Vec<Order> {
+        let mut out = Vec::new(&env);
+        for (_, order) in orders(&env).iter() {
+            if order.owner == owner {
+                out.push_back(order);
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,202 @@
+use soroban_sdk::{
+    contractimpl, contracttype, symbol_short, xdr::ToXdr, Address, BytesN, Env, Map, Symbol, Vec,
+};
+
+use crate::{airdrop, delisting, launch, merkle, PapexToken, PapexTokenClient};
+
+fn k_presale() -> Symbol { symbol_short!("psale") }
+fn k_presale_buys() -> Symbol { symbol_short!("pbuys") }
+
+/// Who may buy in the presale: listed addresses, or holders of a proof for
+/// `sha256(address_xdr)` under the root.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub enum PresaleAccess {
+    AllowList(Vec<Address>),
+    MerkleRoot(BytesN<32>),
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct PresaleTerms {
+    pub access: PresaleAccess,
+    pub price: i128,
+    pub per_address_cap: i128,
+    pub total_cap: i128,
+    pub ends_at: u64,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Presale {
+    pub terms: PresaleTerms,
+    pub sold: i128,
+    pub finalized: bool,
+}
+
+fn read_presale(env: &Env) -> Option<Presale> {
+    env.storage().instance().get(&k_presale())
+}
+
+fn write_presale(env: &Env, presale: &Presale) {
+    env.storage().instance().set(&k_presale(), presale);
+}
+
+/// True while a presale is configured and not yet finalized.
+pub(crate) fn pending(env: &Env) -> bool {
+    read_presale(env).is_some_and(|p| !p.finalized)
+}
+
+/// Finalizes a presale that sold out or ran past `ends_at` and opens curve
+/// trading. Runs lazily from the trading entry points.
+pub(crate) fn settle(env: &Env) {
+    let mut presale = match read_presale(env) {
+        Option::Some(p) if !p.finalized => p,
+        _ => return,
+    };
+    if presale.sold < presale.terms.total_cap && env.ledger().timestamp() < presale.terms.ends_at {
+        return;
+    }
+    presale.finalized = true;
+    write_presale(env, &presale);
+    env.events()
+        .publish((symbol_short!("psend"),), (presale.sold,));
+    PapexToken::apply_trading(env, true);
+}
+
+fn has_access(
+    env: &Env,
+    access: &PresaleAccess,
+    buyer: &Address,
+    proof: &Vec<BytesN<32>>,
+) -> bool {
+    match access {
+        PresaleAccess::AllowList(list) => list.contains(buyer),
+        PresaleAccess::MerkleRoot(root) => {
+            let leaf = env.crypto().sha256(&buyer.clone().to_xdr(env)).to_bytes();
+            merkle::verify(env, root, leaf, proof)
+        }
+    }
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Opens a fixed-price presale ahead of curve trading. The price must
+    /// cover the curve's cost for the whole presale segment, so liquidity
+    /// always backs selling presale tokens back into the curve.
+    pub fn start_presale(env: Env, caller: Address, terms: PresaleTerms) {
+        let cfg = Self::require_owner_call(&env, &caller);
+        if read_presale(&env).is_some() {
+            panic!("presale already configured");
+        }
+        if cfg.trading || launch::started(&env) {
+            panic!("already launched");
+        }
+        if terms.total_cap <= 0
+            || terms.per_address_cap <= 0
+            || terms.per_address_cap > terms.total_cap
+            || terms.ends_at <= env.ledger().timestamp()
+        {
+            panic!("invalid presale terms");
+        }
+        let supply = Self::read_total_supply(&env);
+        if supply + terms.total_cap > cfg.max_supply - airdrop::reserved(&env) {
+            panic!("exceeds max supply");
+        }
+        let curve_supply = Self::curve_supply(&env);
+        let curve =
+            Self::bonding_quote(cfg.base_price, cfg.slope, curve_supply, terms.total_cap, true);
+        if terms.price * terms.total_cap < curve.cost {
+            panic!("presale price below curve");
+        }
+
+        write_presale(&env, &Presale { terms: terms.clone(), sold: 0, finalized: false });
+        env.events().publish(
+            (symbol_short!("psale"),),
+            (terms.price, terms.total_cap, terms.ends_at),
+        );
+    }
+
+    pub fn presale(env: Env) -> Option<Presale> {
+        read_presale(&env)
+    }
+
+    pub fn presale_bought(env: Env, who: Address) -> i128 {
+        let buys: Map<Address, i128> = env
+            .storage()
+            .instance()
+            .get(&k_presale_buys())
+            .unwrap_or(Map::new(&env));
+        buys.get(who).unwrap_or(0)
+    }
+
+    /// Buys `amount` at the presale price. `proof` is only read for a
+    /// Merkle-root presale.
+    pub fn presale_buy(env: Env, buyer: Address, amount: i128, proof: Vec<BytesN<32>>) -> i128 {
+        Self::ensure_amount(amount);
+        buyer.require_auth();
+        Self::ensure_not_paused(&env);
+        settle(&env);
+        delisting::ensure_listed(&env);
+
+        let mut presale = match read_presale(&env) {
+            Option::Some(p) if !p.finalized => p,
+            _ => panic!("no active presale"),
+        };
+        if !has_access(&env, &presale.terms.access, &buyer, &proof) {
+            panic!("not on allow list");
+        }
+        if presale.sold + amount > presale.terms.total_cap {
+            panic!("exceeds presale cap");
+        }
+        let mut buys: Map<Address, i128> = env
+            .storage()
+            .instance()
+            .get(&k_presale_buys())
+            .unwrap_or(Map::new(&env));
+        let bought = buys.get(buyer.clone()).unwrap_or(0) + amount;
+        if bought > presale.terms.per_address_cap {
+            panic!("exceeds per-address cap");
+        }
+
+        let cfg = Self::read_cfg(&env);
+        let cost = presale.terms.price * amount;
+        if let Option::Some(token) = cfg.payment_token.clone() {
+            Self::token_client(&env, &token).transfer(
+                &buyer,
+                &env.current_contract_address(),
+                &cost,
+            );
+        }
+
+        buys.set(buyer.clone(), bought);
+        env.storage().instance().set(&k_presale_buys(), &buys);
+        presale.sold += amount;
+        write_presale(&env, &presale);
+
+        Self::write_total_supply(&env, Self::read_total_supply(&env) + amount);
+        let mut balances = Self::read_balances(&env);
+        let current = balances.get(buyer.clone()).unwrap_or(0);
+        Self::set_balance(&env, &mut balances, &buyer, current + amount);
+        Self::write_balances(&env, &balances);
+        Self::write_liquidity(&env, Self::read_liquidity(&env) + cost);
+
+        env.events()
+            .publish((symbol_short!("pbuy"),), (buyer, amount, cost));
+
+        settle(&env);
+        cost
+    }
+
+    /// Ends a presale that sold out or expired and opens curve trading.
+    /// Callable by anyone; trading entry points also do this lazily.
+    pub fn finalize_presale(env: Env) {
+        if !pending(&env) {
+            panic!("no active presale");
+        }
+        settle(&env);
+        if pending(&env) {
+            panic!("presale still running");
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Map, Symbol};
+
+use crate::{escrow, PapexToken, PapexTokenClient};
+
+fn k_reward_per_token() -> Symbol { symbol_short!("rpt") }
+fn k_reward_states() -> Symbol { symbol_short!("rwd") }
+fn k_reward_pool() -> Symbol { symbol_short!("rpool") }
+
+/// Fixed-point scale of the reward-per-token accumulator.
+const REWARD_PRECISION: i128 = 1_000_000_000_000;
+
+/// Per-holder reward bookkeeping: the accumulator value the holder was last
+/// settled at and the rewards owed but not yet claimed.
+#[derive(Clone)]
+#[contracttype]
+pub struct RewardState {
+    pub paid_per_token: i128,
+    pub owed: i128,
+}
+
+fn reward_per_token(env: &Env) -> i128 {
+    env.storage().instance().get(&k_reward_per_token()).unwrap_or(0)
+}
+
+fn reward_states(env: &Env) -> Map<Address, RewardState> {
+    env.storage()
+        .instance()
+        .get(&k_reward_states())
+        .unwrap_or(Map::new(env))
+}
+
+fn reward_pool(env: &Env) -> i128 {
+    env.storage().instance().get(&k_reward_pool()).unwrap_or(0)
+}
+
+/// Holders without an entry have never been settled, so they are owed
+/// everything accumulated since the start.
+fn accrued(state: Option<RewardState>, acc: i128, balance: i128) -> RewardState {
+    let (paid, owed) = state.map_or((0, 0), |s| (s.paid_per_token, s.owed));
+    RewardState {
+        paid_per_token: acc,
+        owed: owed + balance * (acc - paid) / REWARD_PRECISION,
+    }
+}
+
+/// Part of `who`'s balance that earns rewards: none of it for escrow
+/// accounts.
+fn earning(env: &Env, who: &Address, balance: i128) -> i128 {
+    if escrow::is_escrow(env, who) {
+        0
+    } else {
+        balance
+    }
+}
+
+/// Credits `who` with rewards earned on `balance` since it was last settled.
+/// Must run before every change to `who`'s balance.
+pub(crate) fn settle(env: &Env, who: &Address, balance: i128) {
+    let acc = reward_per_token(env);
+    if acc == 0 {
+        return;
+    }
+    let mut states = reward_states(env);
+    let state = states.get(who.clone());
+    states.set(who.clone(), accrued(state, acc, earning(env, who, balance)));
+    env.storage().instance().set(&k_reward_states(), &states);
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Deposits `amount` of the payment asset to be shared pro-rata among
+    /// current holders, leaving out escrow accounts.
+    pub fn distribute(env: Env, caller: Address, amount: i128) -> i128 {
+        Self::ensure_amount(amount);
+        caller.require_auth();
+        Self::ensure_not_paused(&env);
+        let cfg = Self::read_cfg(&env);
+        let payment = cfg
+            .payment_token
+            .unwrap_or_else(|| panic!("payment token required"));
+        let supply = Self::read_total_supply(&env) - escrow::escrowed_supply(&env);
+        if supply <= 0 {
+            panic!("no holders");
+        }
+
+        Self::token_client(&env, &payment).transfer(
+            &caller,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        let acc = reward_per_token(&env) + amount * REWARD_PRECISION / supply;
+        env.storage().instance().set(&k_reward_per_token(), &acc);
+        env.storage()
+            .instance()
+            .set(&k_reward_pool(), &(reward_pool(&env) + amount));
+
+        env.events()
+            .publish((symbol_short!("dist"),), (caller, amount, acc));
+
+        acc
+    }
+
+    pub fn pending_rewards(env: Env, who: Address) -> i128 {
+        let balance = Self::balance_of(env.clone(), who.clone());
+        let balance = earning(&env, &who, balance);
+        let state = reward_states(&env).get(who);
+        accrued(state, reward_per_token(&env), balance).owed
+    }
+
+    /// Payment asset deposited through `distribute` and not claimed yet.
+    pub fn reward_pool(env: Env) -> i128 {
+        reward_pool(&env)
+    }
+
+    pub fn claim_rewards(env: Env, who: Address) -> i128 {
+        who.require_auth();
+        Self::ensure_not_paused(&env);
+        let balance = Self::balance_of(env.clone(), who.clone());
+        settle(&env, &who, balance);
+
+        let mut states = reward_states(&env);
+        let mut state = match states.get(who.clone()) {
+            Option::Some(state) if state.owed > 0 => state,
+            _ => panic!("nothing to claim"),
+        };
+        let amount = state.owed;
+        state.owed = 0;
+        states.set(who.clone(), state);
+        env.storage().instance().set(&k_reward_states(), &states);
+        env.storage()
+            .instance()
+            .set(&k_reward_pool(), &(reward_pool(&env) - amount));
+
+        let payment = Self::read_cfg(&env).payment_token.unwrap();
+        Self::token_client(&env, &payment).transfer(&env.current_contract_address(), &who, &amount);
+
+        env.events()
+            .publish((symbol_short!("claim"),), (who, amount));
+
+        amount
+    }
+}
@@ -0,0 +1,193 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Symbol};
+
+use crate::{PapexToken, PapexTokenClient};
+
+fn k_snapshot_count() -> Symbol { symbol_short!("nsnap") }
+
+/// Checkpoints are extended to roughly 180 days of ledgers once they fall
+/// below roughly 30 days.
+const CHECKPOINT_TTL_THRESHOLD: u32 = 518_400;
+const CHECKPOINT_TTL_EXTEND_TO: u32 = 3_110_400;
+
+/// Value held at the end of `ledger`.
+#[derive(Clone)]
+#[contracttype]
+pub struct Checkpoint {
+    pub ledger: u32,
+    pub value: i128,
+}
+
+/// Persistent keys, one entry per checkpoint so no holder's history is
+/// loaded with anyone else's.
+#[derive(Clone)]
+#[contracttype]
+pub enum SnapshotKey {
+    BalanceCount(Address),
+    Balance(Address, u32),
+    SupplyCount,
+    Supply(u32),
+    Snapshot(u32),
+}
+
+/// Checkpoint history of one holder's balance or of the total supply.
+enum Series<'a> {
+    Balance(&'a Address),
+    Supply,
+}
+
+impl Series<'_> {
+    fn count_key(&self) -> SnapshotKey {
+        match self {
+            Series::Balance(who) => SnapshotKey::BalanceCount((*who).clone()),
+            Series::Supply => SnapshotKey::SupplyCount,
+        }
+    }
+
+    fn key(&self, index: u32) -> SnapshotKey {
+        match self {
+            Series::Balance(who) => SnapshotKey::Balance((*who).clone(), index),
+            Series::Supply => SnapshotKey::Supply(index),
+        }
+    }
+
+    fn count(&self, env: &Env) -> u32 {
+        env.storage().persistent().get(&self.count_key()).unwrap_or(0)
+    }
+
+    fn get(&self, env: &Env, index: u32) -> Checkpoint {
+        env.storage().persistent().get(&self.key(index)).unwrap()
+    }
+}
+
+fn bump(env: &Env, key: &SnapshotKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, CHECKPOINT_TTL_THRESHOLD, CHECKPOINT_TTL_EXTEND_TO);
+}
+
+/// Records `value` for the current ledger, overwriting a checkpoint already
+/// written in it.
+fn push_checkpoint(env: &Env, series: Series, value: i128) {
+    let ledger = env.ledger().sequence();
+    let count = series.count(env);
+    let index = if count > 0 && series.get(env, count - 1).ledger == ledger {
+        count - 1
+    } else {
+        count
+    };
+    let keys = [series.key(index), series.count_key()];
+    env.storage()
+        .persistent()
+        .set(&keys[0], &Checkpoint { ledger, value });
+    env.storage().persistent().set(&keys[1], &(index + 1));
+    for key in keys.iter() {
+        bump(env, key);
+    }
+}
+
+/// Value as of the start of `ledger`: the last checkpoint written in an
+/// earlier ledger, or zero.
+fn value_at(env: &Env, series: Series, ledger: u32) -> i128 {
+    if ledger > env.ledger().sequence() {
+        panic!("future ledger");
+    }
+    let (mut lo, mut hi) = (0u32, series.count(env));
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if series.get(env, mid).ledger < ledger {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo == 0 {
+        0
+    } else {
+        series.get(env, lo - 1).value
+    }
+}
+
+pub(crate) fn record_balance(env: &Env, who: &Address, balance: i128) {
+    push_checkpoint(env, Series::Balance(who), balance);
+}
+
+pub(crate) fn record_supply(env: &Env, supply: i128) {
+    push_checkpoint(env, Series::Supply, supply);
+}
+
+pub(crate) fn balance_at(env: &Env, who: &Address, ledger: u32) -> i128 {
+    value_at(env, Series::Balance(who), ledger)
+}
+
+pub(crate) fn total_supply_at(env: &Env, ledger: u32) -> i128 {
+    value_at(env, Series::Supply, ledger)
+}
+
+/// Pins the current ledger and returns its snapshot id.
+pub(crate) fn take(env: &Env, by: &Address) -> u32 {
+    let id: u32 = env.storage().instance().get(&k_snapshot_count()).unwrap_or(0);
+    let ledger = env.ledger().sequence();
+    let key = SnapshotKey::Snapshot(id);
+    env.storage().persistent().set(&key, &ledger);
+    bump(env, &key);
+    env.storage()
+        .instance()
+        .set(&k_snapshot_count(), &id.checked_add(1).expect("id overflow"));
+
+    env.events()
+        .publish((symbol_short!("snapshot"),), (id, ledger, by.clone()));
+
+    id
+}
+
+fn snapshot_ledger(env: &Env, snapshot_id: u32) -> u32 {
+    env.storage()
+        .persistent()
+        .get(&SnapshotKey::Snapshot(snapshot_id))
+        .unwrap_or_else(|| panic!("snapshot not found"))
+}
+
+#[contractimpl]
+impl PapexToken {
+    /// Balance `who` held when `ledger` opened, i.e. after every change made
+    /// in earlier ledgers.
+    pub fn balance_at(env: Env, who: Address, ledger: u32) -> i128 {
+        balance_at(&env, &who, ledger)
+    }
+
+    pub fn total_supply_at(env: Env, ledger: u32) -> i128 {
+        total_supply_at(&env, ledger)
+    }
+
+    /// Pins the current ledger for later queries. Snapshot balances are those
+    /// held when the ledger opened, so they cannot change afterwards. Owner
+    /// only; once governance is on, holders take snapshots by proposal.
+    pub fn snapshot(env: Env, caller: Address) -> u32 {
+        Self::require_owner_call(&env, &caller);
+        take(&env, &caller)
+    }
+
+    pub fn snapshot_ledger(env: Env, snapshot_id: u32) -> u32 {
+        snapshot_ledger(&env, snapshot_id)
+    }
+
+    pub fn balance_at_snapshot(env: Env, who: Address, snapshot_id: u32) -> i128 {
+        balance_at(&env, &who, snapshot_ledger(&env, snapshot_id))
+    }
+
+    pub fn total_supply_at_snapshot(env: Env, snapshot_id: u32) -> i128 {
+        total_supply_at(&env, snapshot_ledger(&env, snapshot_id))
+    }
+
+    /// Extends the storage lifetime of `who`'s latest balance checkpoint and
+    /// the latest supply checkpoint. Callable by anyone willing to pay for it.
+    pub fn extend_checkpoints_ttl(env: Env, who: Address) {
+        for series in [Series::Balance(&who), Series::Supply] {
+            let count = series.count(&env);
+            if count > 0 {
+                bump(&env, &series.count_key());
+                bump(&env, &series.key(count - 1));
+            }
+        }
+    }
+}
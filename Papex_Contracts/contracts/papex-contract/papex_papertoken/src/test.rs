@@ -1,10 +1,11 @@
 #![cfg(test)]
+#![allow(clippy::bool_assert_comparison)]
 
 use super::*;
 use core::option::Option;
 use soroban_sdk::{
-    testutils::{Address as _},
-    Address, Env, String,
+    testutils::{Address as _, Ledger},
+    token, xdr::ToXdr, Address, Bytes, BytesN, Env, String, Vec,
 };
 
 #[test]
@@ -40,6 +41,12 @@ fn bonding_curve_flow_without_payment_token() {
     // Owner balance before trade
     assert_eq!(client.balance_of(&owner), 100);
 
+    // The largest purchase a budget covers
+    assert_eq!(client.quote_buy_for_payment(&3_100), 10);
+    assert_eq!(client.quote_buy_for_payment(&3_099), 9);
+    assert_eq!(client.quote_buy_for_payment(&0), 0);
+    assert_eq!(client.quote_buy_for_payment(&10_000_000), 900);
+
     // Buyer purchases 10 tokens
     let quote_buy = client.buy(&buyer, &10, &10_000);
     assert_eq!(quote_buy.cost, 3100); // ((300 + 320)/2) * 10
@@ -54,3 +61,722 @@ fn bonding_curve_flow_without_payment_token() {
     assert_eq!(client.total_supply(), 106);
     assert_eq!(client.summary().liquidity, 11_836);
 }
+
+#[test]
+fn pause_halts_trading_and_transfers() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let owner = Address::generate(&env);
+    let guardian = Address::generate(&env);
+    let holder = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    client.set_trading(&owner, &true);
+    client.set_pauser(&owner, &Option::Some(guardian.clone()));
+
+    client.pause(&guardian);
+    assert!(client.try_buy(&holder, &1, &1_000).is_err());
+    assert!(client.try_sell(&owner, &1, &0).is_err());
+    assert!(client.try_transfer(&owner, &holder, &1).is_err());
+    assert_eq!(client.balance_of(&owner), 100);
+    assert_eq!(client.current_price(), 300);
+
+    assert!(client.try_unpause(&guardian).is_err());
+    client.unpause(&owner);
+    client.transfer(&owner, &holder, &1);
+    assert_eq!(client.balance_of(&holder), 1);
+}
+
+fn init_with_payment_token(
+    env: &Env,
+    owner: &Address,
+    payment: &Address,
+) -> PapexTokenClient<'static> {
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(env, &contract_id);
+    client.init(
+        owner,
+        &String::from_str(env, "Paper Token"),
+        &String::from_str(env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::Some(payment.clone()),
+        &100,
+        &0,
+    );
+    client.set_trading(owner, &true);
+    client
+}
+
+/// Funds `who` with `amount` of the payment asset and pre-approves the
+/// self-spend that `buy` performs.
+fn fund_buyer(env: &Env, payment: &Address, who: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, payment).mint(who, &amount);
+    token::Client::new(env, payment).approve(who, who, &amount, &1_000);
+}
+
+#[test]
+fn rewards_are_shared_pro_rata_across_transfers_and_trades() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let sponsor = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(owner.clone()).address();
+    let payment_client = token::Client::new(&env, &payment);
+    token::StellarAssetClient::new(&env, &payment).mint(&sponsor, &10_000);
+    let client = init_with_payment_token(&env, &owner, &payment);
+
+    client.distribute(&sponsor, &1_000);
+    assert_eq!(client.pending_rewards(&owner), 1_000);
+
+    // Rewards earned before a transfer stay with the sender.
+    client.transfer(&owner, &holder, &50);
+    client.distribute(&sponsor, &1_000);
+    assert_eq!(client.pending_rewards(&owner), 1_500);
+    assert_eq!(client.pending_rewards(&holder), 500);
+
+    fund_buyer(&env, &payment, &buyer, 40_000);
+    client.buy(&buyer, &100, &40_000);
+    client.distribute(&sponsor, &2_000);
+    assert_eq!(client.pending_rewards(&buyer), 1_000);
+    assert_eq!(client.pending_rewards(&holder), 1_000);
+
+    client.sell(&buyer, &100, &0);
+    client.distribute(&sponsor, &1_000);
+    assert_eq!(client.pending_rewards(&buyer), 1_000);
+    assert_eq!(client.pending_rewards(&owner), 2_500);
+
+    assert_eq!(client.claim_rewards(&owner), 2_500);
+    assert_eq!(client.claim_rewards(&holder), 1_500);
+    assert_eq!(client.claim_rewards(&buyer), 1_000);
+    assert_eq!(payment_client.balance(&owner), 2_500);
+    assert_eq!(client.reward_pool(), 0);
+    assert!(client.try_claim_rewards(&owner).is_err());
+
+    // Escrowed tokens, held here for limit orders or by a registered
+    // marketplace, earn nothing while they sit there.
+    let market = Address::generate(&env);
+    assert!(client.try_set_escrow_account(&holder, &market, &true).is_err());
+    client.set_escrow_account(&owner, &market, &true);
+    client.transfer(&owner, &market, &30);
+    client.place_order(&holder, &OrderSide::Sell, &1_000, &30, &0);
+    client.distribute(&sponsor, &400);
+    assert_eq!(client.pending_rewards(&owner), 200);
+    assert_eq!(client.pending_rewards(&holder), 200);
+    assert_eq!(client.pending_rewards(&market), 0);
+    assert_eq!(client.pending_rewards(&client.address), 0);
+
+    client.set_escrow_account(&owner, &market, &false);
+    client.distribute(&sponsor, &700);
+    assert_eq!(client.pending_rewards(&owner), 400);
+    assert_eq!(client.pending_rewards(&market), 300);
+}
+
+#[test]
+fn balances_and_supply_are_checkpointed_per_ledger() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.sequence_number = 10);
+
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    client.set_trading(&owner, &true);
+
+    env.ledger().with_mut(|li| li.sequence_number = 20);
+    assert!(client.try_snapshot(&holder).is_err());
+    let snap = client.snapshot(&owner);
+    client.transfer(&owner, &holder, &40);
+    client.transfer(&holder, &owner, &10);
+    client.buy(&holder, &5, &10_000);
+
+    // The snapshot reflects the ledger's opening state, not same-ledger moves.
+    assert_eq!(client.snapshot_ledger(&snap), 20);
+    assert_eq!(client.balance_at_snapshot(&owner, &snap), 100);
+    assert_eq!(client.balance_at_snapshot(&holder, &snap), 0);
+    assert_eq!(client.total_supply_at_snapshot(&snap), 100);
+
+    env.ledger().with_mut(|li| li.sequence_number = 30);
+    client.sell(&holder, &15, &0);
+    env.ledger().with_mut(|li| li.sequence_number = 40);
+
+    assert_eq!(client.balance_at(&owner, &10), 0);
+    assert_eq!(client.balance_at(&owner, &11), 100);
+    assert_eq!(client.balance_at(&holder, &21), 35);
+    assert_eq!(client.balance_at(&holder, &30), 35);
+    assert_eq!(client.balance_at(&holder, &31), 20);
+    assert_eq!(client.total_supply_at(&25), 105);
+    assert_eq!(client.total_supply_at(&31), 90);
+    assert!(client.try_balance_at(&holder, &41).is_err());
+    client.extend_checkpoints_ttl(&holder);
+}
+
+#[test]
+fn holders_govern_owner_settings_through_timelocked_proposals() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.sequence_number = 10);
+
+    let owner = Address::generate(&env);
+    let holder = Address::generate(&env);
+    let latecomer = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    client.set_trading(&owner, &true);
+    client.transfer(&owner, &holder, &60);
+    let params = GovernanceParams {
+        voting_period: 100,
+        quorum_bps: 5_000,
+        timelock: 50,
+        proposal_threshold: 0,
+    };
+    assert!(client.try_enable_governance(&owner, &params).is_err());
+    client.enable_governance(
+        &owner,
+        &GovernanceParams {
+            proposal_threshold: 10,
+            ..params
+        },
+    );
+    assert!(client.try_set_trading(&owner, &false).is_err());
+    assert!(client.try_withdraw_liquidity(&owner, &1, &owner).is_err());
+    assert!(client.try_pause(&owner).is_err());
+    assert!(client.try_set_pauser(&owner, &Option::Some(owner.clone())).is_err());
+    assert!(client.try_set_pause_controller(&owner, &Option::None).is_err());
+
+    env.ledger().with_mut(|li| {
+        li.sequence_number = 20;
+        li.timestamp = 1_000;
+    });
+    assert!(client
+        .try_propose(&latecomer, &ProposalAction::SetTrading(false))
+        .is_err());
+    let id = client.propose(&holder, &ProposalAction::SetOwner(holder.clone()));
+
+    // Tokens bought after the proposal's snapshot carry no weight.
+    client.buy(&latecomer, &50, &100_000);
+    assert!(client.try_vote(&latecomer, &id, &true).is_err());
+    assert_eq!(client.vote(&holder, &id, &true), 60);
+    assert_eq!(client.vote(&owner, &id, &false), 40);
+    assert!(client.try_vote(&holder, &id, &true).is_err());
+    assert!(client.try_queue_proposal(&id).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 1_100);
+    let queued = client.queue_proposal(&id);
+    assert_eq!(queued.status, ProposalStatus::Queued);
+    assert_eq!(queued.eta, 1_150);
+    assert!(client.try_execute_proposal(&id).is_err());
+    // A passed proposal can no longer be withdrawn by its proposer.
+    assert!(client.try_cancel_proposal(&holder, &id).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 1_150);
+    client.execute_proposal(&id);
+    assert_eq!(client.config().owner, holder);
+    assert_eq!(client.get_proposal(&id).unwrap().status, ProposalStatus::Executed);
+
+    // Low turnout fails quorum even when every vote is in favour.
+    env.ledger().with_mut(|li| li.sequence_number = 30);
+    let id = client.propose(&owner, &ProposalAction::WithdrawLiquidity(500, owner.clone()));
+    client.vote(&owner, &id, &true);
+    env.ledger().with_mut(|li| li.timestamp = 1_300);
+    assert_eq!(client.queue_proposal(&id).status, ProposalStatus::Defeated);
+    assert!(client.try_execute_proposal(&id).is_err());
+
+    // Settings the owner can no longer touch pass through proposals.
+    let market = Address::generate(&env);
+    assert!(client.try_set_escrow_account(&holder, &market, &true).is_err());
+    env.ledger().with_mut(|li| li.sequence_number = 40);
+    let id = client.propose(&holder, &ProposalAction::SetEscrowAccount(market.clone(), true));
+    client.vote(&holder, &id, &true);
+    client.vote(&latecomer, &id, &true);
+    env.ledger().with_mut(|li| li.timestamp = 1_400);
+    client.queue_proposal(&id);
+    env.ledger().with_mut(|li| li.timestamp = 1_450);
+    client.execute_proposal(&id);
+    assert!(client.escrow_accounts().contains(&market));
+}
+
+#[test]
+fn twap_weights_prices_by_time_held() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let owner = Address::generate(&env);
+    let trader = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    client.set_trading(&owner, &true);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_100);
+    client.buy(&trader, &50, &100_000);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_200);
+    assert_eq!(client.twap(&200), 350);
+
+    // A same-timestamp pump carries no weight until time passes.
+    client.buy(&trader, &400, &1_000_000);
+    assert_eq!(client.current_price(), 1_200);
+    assert_eq!(client.twap(&100), 400);
+    client.sell(&trader, &400, &0);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_300);
+    assert_eq!(client.twap(&300), 1_100 / 3);
+
+    let recent = client.observations(&5);
+    assert_eq!(recent.len(), 3);
+    assert_eq!(recent.get(0).unwrap().timestamp, 1_200);
+    assert_eq!(recent.get(0).unwrap().price, 400);
+    assert_eq!(recent.get(2).unwrap().timestamp, 1_000);
+    assert!(client.try_twap(&301).is_err());
+}
+
+#[test]
+fn launch_limits_apply_only_during_the_launch_window() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let owner = Address::generate(&env);
+    let sniper = Address::generate(&env);
+    let friend = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    let limits = LaunchLimits {
+        max_wallet: 30,
+        max_tx: 20,
+        cooldown: 60,
+        window: 600,
+    };
+    client.set_launch_limits(&owner, &limits);
+    assert!(!client.launch_limits().active);
+
+    client.set_trading(&owner, &true);
+    let status = client.launch_limits();
+    assert!(status.active);
+    assert_eq!(status.ends_at, 1_600);
+    assert_eq!(status.limits, limits);
+    assert!(client.try_set_launch_limits(&owner, &LaunchLimits::default()).is_err());
+
+    assert!(client.try_buy(&sniper, &21, &100_000).is_err());
+    client.buy(&sniper, &20, &100_000);
+    assert!(client.try_buy(&sniper, &5, &100_000).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 1_060);
+    assert!(client.try_buy(&sniper, &11, &100_000).is_err());
+    client.buy(&sniper, &10, &100_000);
+    assert!(client.try_transfer(&owner, &sniper, &1).is_err());
+    client.transfer(&sniper, &friend, &10);
+
+    let market = Address::generate(&env);
+    client.set_escrow_account(&owner, &market, &true);
+    client.transfer(&owner, &market, &50);
+    client.transfer(&owner, &contract_id, &50);
+
+    env.ledger().with_mut(|li| li.timestamp = 1_600);
+    assert!(!client.launch_limits().active);
+    client.buy(&sniper, &100, &1_000_000);
+    assert_eq!(client.balance_of(&sniper), 120);
+}
+
+fn address_leaf(env: &Env, who: &Address) -> BytesN<32> {
+    env.crypto().sha256(&who.clone().to_xdr(env)).to_bytes()
+}
+
+/// Parent node for the sorted-pair Merkle trees the token verifies.
+fn merkle_parent(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (lo, hi) = if a.to_array() <= b.to_array() { (a, b) } else { (b, a) };
+    let mut preimage = Bytes::from_array(env, &lo.to_array());
+    preimage.append(&Bytes::from_array(env, &hi.to_array()));
+    env.crypto().sha256(&preimage).to_bytes()
+}
+
+#[test]
+fn merkle_presale_sells_at_fixed_price_then_opens_the_curve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+
+    let owner = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let carol = Address::generate(&env);
+    let mallory = Address::generate(&env);
+    let payment = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::Some(payment.clone()),
+        &100,
+        &0,
+    );
+
+    let alice_leaf = address_leaf(&env, &alice);
+    let carol_leaf = address_leaf(&env, &carol);
+    let root = merkle_parent(&env, &alice_leaf, &carol_leaf);
+    let mut terms = PresaleTerms {
+        access: PresaleAccess::MerkleRoot(root),
+        price: 340,
+        per_address_cap: 30,
+        total_cap: 50,
+        ends_at: 2_000,
+    };
+    // The curve would charge 17,500 for the 50 presale tokens.
+    assert!(client.try_start_presale(&owner, &terms).is_err());
+    terms.price = 400;
+    client.start_presale(&owner, &terms);
+    assert!(client.try_set_trading(&owner, &true).is_err());
+
+    for who in [&alice, &carol, &mallory] {
+        fund_buyer(&env, &payment, who, 100_000);
+    }
+    let alice_proof = Vec::from_array(&env, [carol_leaf.clone()]);
+    let carol_proof = Vec::from_array(&env, [alice_leaf.clone()]);
+    assert_eq!(client.presale_buy(&alice, &30, &alice_proof), 12_000);
+    assert!(client.try_presale_buy(&alice, &1, &alice_proof).is_err());
+    assert!(client.try_presale_buy(&mallory, &1, &alice_proof).is_err());
+    assert!(client.try_presale_buy(&carol, &1, &alice_proof).is_err());
+    assert!(client.try_buy(&carol, &1, &1_000).is_err());
+
+    client.presale_buy(&carol, &10, &carol_proof);
+    assert_eq!(client.presale_bought(&carol), 10);
+    assert_eq!(client.total_supply(), 140);
+    assert_eq!(client.summary().liquidity, 16_000);
+
+    env.ledger().with_mut(|li| li.timestamp = 2_000);
+    assert!(client.try_presale_buy(&carol, &1, &carol_proof).is_err());
+
+    // The curve resumes from the supply including presale tokens.
+    assert_eq!(client.current_price(), 380);
+    client.buy(&mallory, &1, &1_000);
+    let presale = client.presale().unwrap();
+    assert!(presale.finalized);
+    assert_eq!(presale.sold, 40);
+    assert!(client.config().trading);
+}
+
+fn airdrop_leaf(env: &Env, index: u32, who: &Address, amount: i128) -> BytesN<32> {
+    let mut preimage = Bytes::from_array(env, &index.to_be_bytes());
+    preimage.append(&who.clone().to_xdr(env));
+    preimage.append(&Bytes::from_array(env, &amount.to_be_bytes()));
+    env.crypto().sha256(&preimage).to_bytes()
+}
+
+#[test]
+fn airdrop_claims_are_proven_once_and_reserve_supply() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let owner = Address::generate(&env);
+    let reviewer = Address::generate(&env);
+    let citer = Address::generate(&env);
+    let buyer = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexToken);
+    let client = PapexTokenClient::new(&env, &contract_id);
+    client.init(
+        &owner,
+        &String::from_str(&env, "Paper Token"),
+        &String::from_str(&env, "PAPER"),
+        &1_000,
+        &100,
+        &2,
+        &Option::None,
+        &100,
+        &10_000,
+    );
+    client.set_trading(&owner, &true);
+
+    let reviewer_leaf = airdrop_leaf(&env, 0, &reviewer, 25);
+    let citer_leaf = airdrop_leaf(&env, 1, &citer, 15);
+    let root = merkle_parent(&env, &reviewer_leaf, &citer_leaf);
+    assert!(client.try_start_airdrop(&owner, &root, &901).is_err());
+    let id = client.start_airdrop(&owner, &root, &50);
+    assert_eq!(client.airdrop_reserved(), 50);
+
+    // Reserved supply is off limits to the curve.
+    assert!(client.try_buy(&buyer, &851, &10_000_000).is_err());
+
+    let reviewer_proof = Vec::from_array(&env, [citer_leaf.clone()]);
+    assert!(client.try_claim(&reviewer, &id, &0, &30, &reviewer_proof).is_err());
+    assert!(client.try_claim(&citer, &id, &0, &25, &reviewer_proof).is_err());
+    let price = client.current_price();
+    let sell_quote = client.quote_sell(&10);
+    assert_eq!(client.claim(&reviewer, &id, &0, &25, &reviewer_proof), 25);
+    // Claims bring no liquidity, so the curve does not move.
+    assert_eq!(client.airdrop_unbacked(), 25);
+    assert_eq!(client.current_price(), price);
+    assert_eq!(client.quote_sell(&10).cost, sell_quote.cost);
+    assert!(client.is_claimed(&id, &0));
+    assert!(!client.is_claimed(&id, &1));
+    assert!(client.try_claim(&reviewer, &id, &0, &25, &reviewer_proof).is_err());
+
+    assert_eq!(client.balance_of(&reviewer), 25);
+    assert_eq!(client.total_supply(), 125);
+    assert_eq!(client.airdrop_reserved(), 25);
+
+    assert_eq!(client.close_airdrop(&owner, &id), 25);
+    assert_eq!(client.airdrop_reserved(), 0);
+    let citer_proof = Vec::from_array(&env, [reviewer_leaf]);
+    assert!(client.try_claim(&citer, &id, &1, &15, &citer_proof).is_err());
+    assert!(client.get_airdrop(&id).unwrap().closed);
+}
+
+#[test]
+fn limit_orders_fill_when_trades_move_the_curve() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 100);
+
+    let owner = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let dave = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    let payment = env
+        .register_stellar_asset_contract_v2(Address::generate(&env))
+        .address();
+    let pay = token::Client::new(&env, &payment);
+    let client = init_with_payment_token(&env, &owner, &payment);
+    fund_buyer(&env, &payment, &bob, 100_000);
+    fund_buyer(&env, &payment, &carol, 10_000);
+    client.transfer(&owner, &alice, &20);
+    client.transfer(&owner, &dave, &10);
+
+    // Curve price is 300 at supply 100, so a bid at 400 would fill now.
+    assert!(client
+        .try_place_order(&carol, &OrderSide::Buy, &400, &5, &0)
+        .is_err());
+    let ask = client.place_order(&alice, &OrderSide::Sell, &330, &20, &0);
+    let stale = client.place_order(&dave, &OrderSide::Sell, &300, &10, &150);
+    assert_eq!(client.balance_of(&alice), 0);
+    assert_eq!(client.orders().len(), 2);
+
+    env.ledger().with_mut(|li| li.timestamp = 200);
+    client.buy(&bob, &30, &10_000);
+
+    // The expired ask is evicted and its tokens handed back; alice's ask
+    // fills at an average of 340, credited for her to claim.
+    assert!(client.get_order(&ask).is_none());
+    assert!(client.get_order(&stale).is_none());
+    assert_eq!(client.balance_of(&dave), 10);
+    assert_eq!(pay.balance(&alice), 0);
+    assert_eq!(client.proceeds_of(&alice), 6_800);
+    assert_eq!(client.claim_proceeds(&alice), 6_800);
+    assert_eq!(pay.balance(&alice), 6_800);
+    assert!(client.try_claim_proceeds(&alice).is_err());
+    assert_eq!(client.total_supply(), 110);
+
+    let bid = client.place_order(&carol, &OrderSide::Buy, &310, &15, &0);
+    assert_eq!(pay.balance(&carol), 10_000 - 4_650);
+    client.sell(&bob, &10, &0);
+
+    // The bid fills 10 of 15 before the average price would exceed 310.
+    let open = client.get_order(&bid).unwrap();
+    assert_eq!(open.remaining, 5);
+    assert_eq!(open.escrow, 1_550);
+    assert_eq!(client.balance_of(&carol), 10);
+    assert_eq!(client.orders_of(&carol).len(), 1);
+
+    assert!(client.try_cancel_order(&bob, &bid).is_err());
+    client.cancel_order(&carol, &bid);
+    assert_eq!(pay.balance(&carol), 10_000 - 3_100);
+    assert!(client.orders().is_empty());
+
+    // Dust orders are rejected and each owner has a small quota, freed as
+    // their orders expire.
+    assert!(client
+        .try_place_order(&dave, &OrderSide::Sell, &900, &1, &0)
+        .is_err());
+    client.place_order(&dave, &OrderSide::Sell, &1_000, &1, &300);
+    for _ in 0..4 {
+        client.place_order(&dave, &OrderSide::Sell, &1_000, &1, &0);
+    }
+    assert!(client
+        .try_place_order(&dave, &OrderSide::Sell, &1_000, &1, &0)
+        .is_err());
+    client.place_order(&bob, &OrderSide::Sell, &1_000, &1, &0);
+    env.ledger().with_mut(|li| li.timestamp = 300);
+    client.place_order(&dave, &OrderSide::Sell, &1_000, &1, &0);
+    assert_eq!(client.orders_of(&dave).len(), 5);
+    assert_eq!(client.balance_of(&dave), 5);
+}
+
+#[test]
+fn delisting_allows_sells_then_pro_rata_redemption() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let owner = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let marketplace = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(owner.clone()).address();
+    let payment_client = token::Client::new(&env, &payment);
+    fund_buyer(&env, &payment, &buyer, 20_000);
+    let client = init_with_payment_token(&env, &owner, &payment);
+    client.buy(&buyer, &50, &17_500);
+    assert_eq!(client.summary().liquidity, 17_500);
+
+    let (week, month) = (7 * 86_400, 30 * 86_400);
+    let (notice_ends, redemption_ends) = (1_000 + week, 1_000 + week + month);
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+    assert!(client
+        .try_start_delisting(&marketplace, &notice_ends, &redemption_ends)
+        .is_err());
+    client.set_delister(&owner, &Option::Some(marketplace.clone()));
+    // The token enforces the minimum windows itself.
+    assert!(client
+        .try_start_delisting(&marketplace, &(notice_ends - 1), &redemption_ends)
+        .is_err());
+    assert!(client
+        .try_start_delisting(&marketplace, &notice_ends, &(redemption_ends - 1))
+        .is_err());
+    client.start_delisting(&marketplace, &notice_ends, &redemption_ends);
+    assert!(client
+        .try_start_delisting(&marketplace, &notice_ends, &redemption_ends)
+        .is_err());
+    assert_eq!(client.delisting_phase(), DelistPhase::Notice);
+
+    // Notice: sells only, and the reserve stays put.
+    assert!(client.try_buy(&buyer, &1, &1_000).is_err());
+    assert!(client.try_withdraw_liquidity(&owner, &1, &owner).is_err());
+    assert!(client.try_redeem(&buyer, &1).is_err());
+    assert_eq!(client.sell(&buyer, &10, &0).cost, 3_900);
+
+    // Redemption: 13_600 liquidity against 140 supply. Pausing does not
+    // hold redemptions up.
+    env.ledger().with_mut(|li| li.timestamp = notice_ends);
+    assert_eq!(client.delisting_phase(), DelistPhase::Redemption);
+    assert!(client.try_sell(&buyer, &1, &0).is_err());
+    client.pause(&owner);
+    assert_eq!(client.quote_redeem(&100), 9_714);
+    assert_eq!(client.redeem(&owner, &100), 9_714);
+
+    // After the window the rest of the reserve still belongs to holders.
+    env.ledger().with_mut(|li| li.timestamp = redemption_ends);
+    assert_eq!(client.delisting_phase(), DelistPhase::Closed);
+    assert!(client.try_withdraw_liquidity(&owner, &1, &owner).is_err());
+    assert_eq!(client.redeem(&buyer, &40), 3_886);
+    assert_eq!(client.total_supply(), 0);
+    assert_eq!(client.summary().liquidity, 0);
+    assert_eq!(payment_client.balance(&buyer), 20_000 - 17_500 + 3_900 + 3_886);
+}
+
+#[test]
+fn governed_token_appoints_its_delister_by_proposal() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let owner = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let marketplace = Address::generate(&env);
+
+    let payment = env.register_stellar_asset_contract_v2(owner.clone()).address();
+    fund_buyer(&env, &payment, &buyer, 20_000);
+    let client = init_with_payment_token(&env, &owner, &payment);
+    client.buy(&buyer, &50, &17_500);
+    client.enable_governance(
+        &owner,
+        &GovernanceParams {
+            voting_period: 100,
+            quorum_bps: 5_000,
+            timelock: 50,
+            proposal_threshold: 10,
+        },
+    );
+    let appoint = Option::Some(marketplace.clone());
+    assert!(client.try_set_delister(&owner, &appoint).is_err());
+
+    env.ledger().with_mut(|li| {
+        li.sequence_number = 10;
+        li.timestamp = 1_000;
+    });
+    let id = client.propose(&owner, &ProposalAction::SetDelister(appoint));
+    client.vote(&owner, &id, &true);
+    env.ledger().with_mut(|li| li.timestamp = 1_100);
+    client.queue_proposal(&id);
+    env.ledger().with_mut(|li| li.timestamp = 1_150);
+    client.execute_proposal(&id);
+    assert_eq!(client.delister(), Option::Some(marketplace.clone()));
+
+    let notice_ends = 1_150 + 7 * 86_400;
+    client.start_delisting(&marketplace, &notice_ends, &(notice_ends + 30 * 86_400));
+    assert_eq!(client.delisting_phase(), DelistPhase::Notice);
+    assert!(client.try_buy(&buyer, &1, &1_000).is_err());
+}
@@ -0,0 +1,515 @@
+use soroban_sdk::token;
+use soroban_sdk::{
+    contractclient, contractimpl, contracttype, symbol_short, Address, Env, Map, String, Vec,
+};
+
+use crate::{PapexRegistry, PapexRegistryClient, PaperStatus};
+
+fn k_campaigns() -> soroban_sdk::Symbol { symbol_short!("cmp") }
+fn k_next_campaign() -> soroban_sdk::Symbol { symbol_short!("ncmp") }
+fn k_pledges() -> soroban_sdk::Symbol { symbol_short!("plg") }
+
+const MAX_MILESTONES: u32 = 10;
+/// How long a funded campaign may go without releasing its next milestone
+/// before backers can take back what is still in escrow.
+const MILESTONE_TIMEOUT: u64 = 180 * 86_400;
+
+/// Checkpointed views of a paper token used to weigh holder votes.
+#[contractclient(name = "PaperTokenClient")]
+pub trait PaperToken {
+    fn balance_at(env: Env, who: Address, ledger: u32) -> i128;
+    fn total_supply_at(env: Env, ledger: u32) -> i128;
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[contracttype]
+pub enum CampaignStatus {
+    Open,
+    Funded,
+    Failed,
+    Completed,
+}
+
+/// Who decides that a milestone has been delivered.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub enum Approval {
+    /// Designated reviewers; the second field is how many must approve.
+    Reviewers(Vec<Address>, u32),
+    /// Holders of the paper token vote. Fields are the quorum in basis points
+    /// of total supply and the voting period in seconds.
+    HolderVote(u32, u64),
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct MilestoneSpec {
+    pub description: String,
+    pub amount: i128,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct CampaignTerms {
+    pub asset: Address,
+    pub goal: i128,
+    pub deadline: u64,
+    pub milestones: Vec<MilestoneSpec>,
+    pub approval: Approval,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Milestone {
+    pub description: String,
+    pub amount: i128,
+    pub released: bool,
+    pub approvals: Vec<Address>,
+    pub votes_for: i128,
+    pub votes_against: i128,
+    pub vote_ends_at: u64,
+    /// Ledger whose opening balances weigh the current holder vote.
+    pub vote_ledger: u32,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Campaign {
+    pub id: u32,
+    pub paper_id: u32,
+    pub owner: Address,
+    pub asset: Address,
+    pub goal: i128,
+    pub raised: i128,
+    pub deadline: u64,
+    pub approval: Approval,
+    /// Paper token weighing holder votes, fixed when the campaign opens.
+    pub token: Option<Address>,
+    pub milestones: Vec<Milestone>,
+    pub next_milestone: u32,
+    /// When a funded campaign fails unless its next milestone is released.
+    pub stalls_at: u64,
+    pub status: CampaignStatus,
+}
+
+fn campaigns(env: &Env) -> Map<u32, Campaign> {
+    env.storage()
+        .instance()
+        .get(&k_campaigns())
+        .unwrap_or(Map::new(env))
+}
+
+fn read_campaign(env: &Env, campaign_id: u32) -> Campaign {
+    campaigns(env)
+        .get(campaign_id)
+        .unwrap_or_else(|| panic!("campaign not found"))
+}
+
+fn write_campaign(env: &Env, campaign: &Campaign) {
+    let mut all = campaigns(env);
+    all.set(campaign.id, campaign.clone());
+    env.storage().instance().set(&k_campaigns(), &all);
+}
+
+fn pledges(env: &Env) -> Map<u32, Map<Address, i128>> {
+    env.storage()
+        .instance()
+        .get(&k_pledges())
+        .unwrap_or(Map::new(env))
+}
+
+/// Moves an open campaign past its deadline, or a funded one past
+/// `stalls_at`, into `Failed`.
+fn settle_deadline(env: &Env, campaign: &mut Campaign) {
+    let now = env.ledger().timestamp();
+    let expired = match campaign.status {
+        CampaignStatus::Open => now >= campaign.deadline,
+        CampaignStatus::Funded => now >= campaign.stalls_at,
+        _ => false,
+    };
+    if expired {
+        campaign.status = CampaignStatus::Failed;
+    }
+}
+
+/// Reads a campaign that is funded and not yet stalled.
+fn read_funded(env: &Env, campaign_id: u32) -> Campaign {
+    let mut campaign = read_campaign(env, campaign_id);
+    settle_deadline(env, &mut campaign);
+    if campaign.status != CampaignStatus::Funded {
+        panic!("campaign not funded");
+    }
+    campaign
+}
+
+fn vote_token(campaign: &Campaign) -> Address {
+    campaign
+        .token
+        .clone()
+        .unwrap_or_else(|| panic!("not a holder vote"))
+}
+
+fn released_amount(campaign: &Campaign) -> i128 {
+    campaign
+        .milestones
+        .iter()
+        .filter(|m| m.released)
+        .map(|m| m.amount)
+        .sum()
+}
+
+#[contractimpl]
+impl PapexRegistry {
+    /// Opens a funding campaign for a paper. Milestone amounts must add up to
+    /// the goal; pledges are held in escrow by the registry.
+    pub fn open_campaign(env: Env, caller: Address, paper_id: u32, terms: CampaignTerms) -> u32 {
+        caller.require_auth();
+        Self::ensure_not_paused(&env);
+        let paper = Self::papers(&env)
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("paper not found"));
+        if caller != paper.owner {
+            panic!("only owner");
+        }
+        Self::ensure_not_embargoed(&paper);
+        if paper.status == PaperStatus::Archived {
+            panic!("paper archived");
+        }
+
+        if terms.goal <= 0 || terms.deadline <= env.ledger().timestamp() {
+            panic!("invalid campaign");
+        }
+        if terms.milestones.is_empty() || terms.milestones.len() > MAX_MILESTONES {
+            panic!("invalid milestones");
+        }
+        let mut milestones = Vec::new(&env);
+        let mut total = 0i128;
+        for spec in terms.milestones.iter() {
+            if spec.amount <= 0 {
+                panic!("invalid milestones");
+            }
+            total += spec.amount;
+            milestones.push_back(Milestone {
+                description: spec.description,
+                amount: spec.amount,
+                released: false,
+                approvals: Vec::new(&env),
+                votes_for: 0,
+                votes_against: 0,
+                vote_ends_at: 0,
+                vote_ledger: 0,
+            });
+        }
+        if total != terms.goal {
+            panic!("milestones must sum to goal");
+        }
+        let token = match &terms.approval {
+            Approval::Reviewers(reviewers, needed) => {
+                if *needed == 0 || *needed > reviewers.len() {
+                    panic!("invalid reviewers");
+                }
+                Option::None
+            }
+            Approval::HolderVote(quorum_bps, period) => {
+                if *quorum_bps == 0 || *quorum_bps > 10_000 || *period == 0 {
+                    panic!("invalid vote terms");
+                }
+                if paper.token.is_none() {
+                    panic!("paper not tokenized");
+                }
+                paper.token.clone()
+            }
+        };
+
+        let campaign_id: u32 = env
+            .storage()
+            .instance()
+            .get(&k_next_campaign())
+            .unwrap_or(0u32);
+        env.storage()
+            .instance()
+            .set(&k_next_campaign(), &campaign_id.checked_add(1).expect("id overflow"));
+
+        let campaign = Campaign {
+            id: campaign_id,
+            paper_id,
+            owner: caller.clone(),
+            asset: terms.asset,
+            goal: terms.goal,
+            raised: 0,
+            deadline: terms.deadline,
+            approval: terms.approval,
+            token,
+            milestones,
+            next_milestone: 0,
+            stalls_at: 0,
+            status: CampaignStatus::Open,
+        };
+        write_campaign(&env, &campaign);
+
+        env.events().publish(
+            (symbol_short!("campaign"),),
+            (campaign_id, paper_id, caller, campaign.goal, campaign.deadline),
+        );
+
+        campaign_id
+    }
+
+    pub fn get_campaign(env: Env, campaign_id: u32) -> Option<Campaign> {
+        campaigns(&env).get(campaign_id)
+    }
+
+    pub fn pledge_of(env: Env, campaign_id: u32, backer: Address) -> i128 {
+        pledges(&env)
+            .get(campaign_id)
+            .and_then(|book| book.get(backer))
+            .unwrap_or(0)
+    }
+
+    /// Escrows `amount` of the campaign asset. Pledges stop at the goal, and
+    /// reaching it marks the campaign as funded.
+    pub fn pledge(env: Env, backer: Address, campaign_id: u32, amount: i128) -> Campaign {
+        backer.require_auth();
+        Self::ensure_not_paused(&env);
+        if amount <= 0 {
+            panic!("amount must be positive");
+        }
+        let mut campaign = read_campaign(&env, campaign_id);
+        settle_deadline(&env, &mut campaign);
+        if campaign.status != CampaignStatus::Open {
+            panic!("campaign not open");
+        }
+        if campaign.raised + amount > campaign.goal {
+            panic!("exceeds goal");
+        }
+
+        token::Client::new(&env, &campaign.asset).transfer(
+            &backer,
+            &env.current_contract_address(),
+            &amount,
+        );
+
+        let mut all = pledges(&env);
+        let mut book = all.get(campaign_id).unwrap_or(Map::new(&env));
+        let pledged = book.get(backer.clone()).unwrap_or(0);
+        book.set(backer.clone(), pledged + amount);
+        all.set(campaign_id, book);
+        env.storage().instance().set(&k_pledges(), &all);
+
+        campaign.raised += amount;
+        if campaign.raised == campaign.goal {
+            campaign.status = CampaignStatus::Funded;
+            campaign.stalls_at = env.ledger().timestamp().saturating_add(MILESTONE_TIMEOUT);
+        }
+        write_campaign(&env, &campaign);
+
+        env.events().publish(
+            (symbol_short!("pledge"),),
+            (campaign_id, backer, amount, campaign.raised),
+        );
+
+        campaign
+    }
+
+    /// Lets the owner call off a campaign that has not reached its goal, so
+    /// backers can take their pledges back.
+    pub fn cancel_campaign(env: Env, caller: Address, campaign_id: u32) {
+        caller.require_auth();
+        let mut campaign = read_campaign(&env, campaign_id);
+        if caller != campaign.owner {
+            panic!("only owner");
+        }
+        if campaign.status != CampaignStatus::Open {
+            panic!("campaign not open");
+        }
+        campaign.status = CampaignStatus::Failed;
+        write_campaign(&env, &campaign);
+        env.events()
+            .publish((symbol_short!("cmpfail"),), (campaign_id,));
+    }
+
+    /// Returns a backer's pledge once the campaign has failed to reach its
+    /// goal by the deadline or was cancelled. A funded campaign that goes
+    /// 180 days without releasing a milestone fails too, and then refunds
+    /// each backer their share of what is still in escrow.
+    pub fn refund(env: Env, backer: Address, campaign_id: u32) -> i128 {
+        backer.require_auth();
+        let mut campaign = read_campaign(&env, campaign_id);
+        settle_deadline(&env, &mut campaign);
+        if campaign.status != CampaignStatus::Failed {
+            panic!("campaign not failed");
+        }
+
+        let mut all = pledges(&env);
+        let mut book = all.get(campaign_id).unwrap_or(Map::new(&env));
+        let pledged = book.get(backer.clone()).unwrap_or(0);
+        if pledged <= 0 {
+            panic!("nothing to refund");
+        }
+        book.remove(backer.clone());
+        all.set(campaign_id, book);
+        env.storage().instance().set(&k_pledges(), &all);
+
+        let amount = pledged * (campaign.goal - released_amount(&campaign)) / campaign.goal;
+        campaign.raised -= pledged;
+        write_campaign(&env, &campaign);
+
+        token::Client::new(&env, &campaign.asset).transfer(
+            &env.current_contract_address(),
+            &backer,
+            &amount,
+        );
+        env.events()
+            .publish((symbol_short!("refund"),), (campaign_id, backer, amount));
+
+        amount
+    }
+
+    /// Reviewer sign-off on the next unreleased milestone.
+    pub fn approve_milestone(env: Env, reviewer: Address, campaign_id: u32) -> Milestone {
+        reviewer.require_auth();
+        let mut campaign = read_funded(&env, campaign_id);
+        match &campaign.approval {
+            Approval::Reviewers(reviewers, _) if reviewers.contains(&reviewer) => {}
+            _ => panic!("not a reviewer"),
+        }
+
+        let index = campaign.next_milestone;
+        let mut milestone = campaign.milestones.get(index).unwrap();
+        if milestone.approvals.contains(&reviewer) {
+            panic!("already approved");
+        }
+        milestone.approvals.push_back(reviewer.clone());
+        campaign.milestones.set(index, milestone.clone());
+        write_campaign(&env, &campaign);
+
+        env.events()
+            .publish((symbol_short!("msapprove"),), (campaign_id, index, reviewer));
+
+        milestone
+    }
+
+    /// Opens a holder vote on the next milestone. A failed vote can be
+    /// reopened by the owner.
+    pub fn request_release(env: Env, caller: Address, campaign_id: u32) -> Milestone {
+        caller.require_auth();
+        let mut campaign = read_funded(&env, campaign_id);
+        if caller != campaign.owner {
+            panic!("only owner");
+        }
+        let period = match campaign.approval {
+            Approval::HolderVote(_, period) => period,
+            _ => panic!("not a holder vote"),
+        };
+
+        let index = campaign.next_milestone;
+        let mut milestone = campaign.milestones.get(index).unwrap();
+        let now = env.ledger().timestamp();
+        if milestone.vote_ends_at > now {
+            panic!("vote in progress");
+        }
+        milestone.approvals = Vec::new(&env);
+        milestone.votes_for = 0;
+        milestone.votes_against = 0;
+        milestone.vote_ends_at = now + period;
+        milestone.vote_ledger = env.ledger().sequence();
+        campaign.milestones.set(index, milestone.clone());
+        write_campaign(&env, &campaign);
+
+        env.events().publish(
+            (symbol_short!("msvote"),),
+            (campaign_id, index, milestone.vote_ends_at),
+        );
+
+        milestone
+    }
+
+    /// Casts a holder vote weighted by the voter's balance, when the vote
+    /// opened, of the paper token fixed at opening, so tokens moved
+    /// afterwards cannot vote twice and re-tokenizing the paper changes
+    /// nothing.
+    pub fn vote_milestone(env: Env, voter: Address, campaign_id: u32, support: bool) -> Milestone {
+        voter.require_auth();
+        let mut campaign = read_funded(&env, campaign_id);
+        if !matches!(campaign.approval, Approval::HolderVote(_, _)) {
+            panic!("not a holder vote");
+        }
+        let index = campaign.next_milestone;
+        let mut milestone = campaign.milestones.get(index).unwrap();
+        if env.ledger().timestamp() >= milestone.vote_ends_at {
+            panic!("voting closed");
+        }
+        if milestone.approvals.contains(&voter) {
+            panic!("already voted");
+        }
+
+        let token = vote_token(&campaign);
+        let weight =
+            PaperTokenClient::new(&env, &token).balance_at(&voter, &milestone.vote_ledger);
+        if weight <= 0 {
+            panic!("no voting power");
+        }
+        milestone.approvals.push_back(voter.clone());
+        if support {
+            milestone.votes_for += weight;
+        } else {
+            milestone.votes_against += weight;
+        }
+        campaign.milestones.set(index, milestone.clone());
+        write_campaign(&env, &campaign);
+
+        env.events()
+            .publish((symbol_short!("vote"),), (campaign_id, index, voter, support, weight));
+
+        milestone
+    }
+
+    /// Pays the next milestone to the campaign owner once it is approved by
+    /// enough reviewers, or once its holder vote has closed with quorum and a
+    /// majority in favour.
+    pub fn release_milestone(env: Env, campaign_id: u32) -> Milestone {
+        let mut campaign = read_funded(&env, campaign_id);
+        let index = campaign.next_milestone;
+        let mut milestone = campaign.milestones.get(index).unwrap();
+
+        let approved = match &campaign.approval {
+            Approval::Reviewers(_, needed) => milestone.approvals.len() >= *needed,
+            Approval::HolderVote(quorum_bps, _) => {
+                if milestone.vote_ends_at == 0 || env.ledger().timestamp() < milestone.vote_ends_at
+                {
+                    panic!("vote not finished");
+                }
+                let token = vote_token(&campaign);
+                let supply = PaperTokenClient::new(&env, &token)
+                    .total_supply_at(&milestone.vote_ledger);
+                let turnout = milestone.votes_for + milestone.votes_against;
+                turnout * 10_000 >= supply * (*quorum_bps as i128)
+                    && milestone.votes_for > milestone.votes_against
+            }
+        };
+        if !approved {
+            panic!("milestone not approved");
+        }
+
+        milestone.released = true;
+        campaign.milestones.set(index, milestone.clone());
+        campaign.next_milestone += 1;
+        campaign.stalls_at = env.ledger().timestamp().saturating_add(MILESTONE_TIMEOUT);
+        if campaign.next_milestone == campaign.milestones.len() {
+            campaign.status = CampaignStatus::Completed;
+        }
+        write_campaign(&env, &campaign);
+
+        token::Client::new(&env, &campaign.asset).transfer(
+            &env.current_contract_address(),
+            &campaign.owner,
+            &milestone.amount,
+        );
+        env.events().publish(
+            (symbol_short!("release"),),
+            (campaign_id, index, milestone.amount),
+        );
+
+        milestone
+    }
+}
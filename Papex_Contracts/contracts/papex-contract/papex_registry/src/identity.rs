@@ -0,0 +1,184 @@
+use soroban_sdk::{contractimpl, contracttype, symbol_short, Address, Env, Map, String, Vec};
+
+use crate::{PapexRegistry, PapexRegistryClient};
+
+fn k_attesters() -> soroban_sdk::Symbol { symbol_short!("atst") }
+fn k_identities() -> soroban_sdk::Symbol { symbol_short!("idn") }
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+#[contracttype]
+pub enum IdentityKind {
+    Orcid,
+    Institution,
+}
+
+/// Statement by an approved attester that `value` (an ORCID iD or an
+/// institution name) belongs to the subject address. `expires_at == 0`
+/// means the attestation does not expire.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Attestation {
+    pub attester: Address,
+    pub kind: IdentityKind,
+    pub value: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+    pub revoked: bool,
+}
+
+fn attesters(env: &Env) -> Map<Address, bool> {
+    env.storage()
+        .instance()
+        .get(&k_attesters())
+        .unwrap_or(Map::new(env))
+}
+
+fn identities(env: &Env) -> Map<Address, Vec<Attestation>> {
+    env.storage()
+        .instance()
+        .get(&k_identities())
+        .unwrap_or(Map::new(env))
+}
+
+fn is_valid(env: &Env, approved: &Map<Address, bool>, att: &Attestation) -> bool {
+    !att.revoked
+        && (att.expires_at == 0 || att.expires_at > env.ledger().timestamp())
+        && approved.get(att.attester.clone()).unwrap_or(false)
+}
+
+/// True if `who` holds at least one live attestation from a currently
+/// approved attester.
+pub(crate) fn is_verified(env: &Env, who: &Address) -> bool {
+    let approved = attesters(env);
+    identities(env)
+        .get(who.clone())
+        .map(|list| list.iter().any(|att| is_valid(env, &approved, &att)))
+        .unwrap_or(false)
+}
+
+#[contractimpl]
+impl PapexRegistry {
+    pub fn set_attester(env: Env, caller: Address, attester: Address, approved: bool) {
+        Self::require_admin(&env, &caller);
+        let mut list = attesters(&env);
+        if approved {
+            list.set(attester.clone(), true);
+        } else {
+            list.remove(attester.clone());
+        }
+        env.storage().instance().set(&k_attesters(), &list);
+        env.events()
+            .publish((symbol_short!("attester"),), (attester, approved));
+    }
+
+    pub fn is_attester(env: Env, who: Address) -> bool {
+        attesters(&env).get(who).unwrap_or(false)
+    }
+
+    /// Links `subject` to an identity. Both the attester and the subject sign,
+    /// and a new attestation replaces the attester's earlier one of the same
+    /// kind.
+    pub fn attest(
+        env: Env,
+        attester: Address,
+        subject: Address,
+        kind: IdentityKind,
+        value: String,
+        expires_at: u64,
+    ) -> Attestation {
+        attester.require_auth();
+        subject.require_auth();
+        if !attesters(&env).get(attester.clone()).unwrap_or(false) {
+            panic!("not an attester");
+        }
+        if value.is_empty() {
+            panic!("identity required");
+        }
+        let now = env.ledger().timestamp();
+        if expires_at != 0 && expires_at <= now {
+            panic!("invalid expiry");
+        }
+
+        let attestation = Attestation {
+            attester: attester.clone(),
+            kind,
+            value,
+            issued_at: now,
+            expires_at,
+            revoked: false,
+        };
+
+        let mut all = identities(&env);
+        let mut list = Vec::new(&env);
+        for existing in all.get(subject.clone()).unwrap_or(Vec::new(&env)).iter() {
+            if existing.attester != attester || existing.kind != kind {
+                list.push_back(existing);
+            }
+        }
+        list.push_back(attestation.clone());
+        all.set(subject.clone(), list);
+        env.storage().instance().set(&k_identities(), &all);
+
+        env.events().publish(
+            (symbol_short!("attest"),),
+            (attester, subject, kind as u32, expires_at),
+        );
+
+        attestation
+    }
+
+    /// Revokes an attestation. Allowed for the attester that issued it, the
+    /// subject, or the admin.
+    pub fn revoke_attestation(
+        env: Env,
+        caller: Address,
+        subject: Address,
+        attester: Address,
+        kind: IdentityKind,
+    ) {
+        caller.require_auth();
+        if caller != attester && caller != subject && caller != Self::read_admin(&env) {
+            panic!("not authorized");
+        }
+
+        let mut all = identities(&env);
+        let mut list = all
+            .get(subject.clone())
+            .unwrap_or_else(|| panic!("attestation not found"));
+        let index = list
+            .iter()
+            .position(|att| att.attester == attester && att.kind == kind)
+            .unwrap_or_else(|| panic!("attestation not found")) as u32;
+        let mut att = list.get(index).unwrap();
+        att.revoked = true;
+        list.set(index, att);
+        all.set(subject.clone(), list);
+        env.storage().instance().set(&k_identities(), &all);
+
+        env.events()
+            .publish((symbol_short!("arevoke"),), (attester, subject, kind as u32));
+    }
+
+    /// Live attestations for `who`: not revoked, not expired, and issued by
+    /// an attester that is still approved.
+    pub fn identity_of(env: Env, who: Address) -> Vec<Attestation> {
+        let approved = attesters(&env);
+        let mut out = Vec::new(&env);
+        for att in identities(&env).get(who).unwrap_or(Vec::new(&env)).iter() {
+            if is_valid(&env, &approved, &att) {
+                out.push_back(att);
+            }
+        }
+        out
+    }
+
+    /// Every attestation ever issued for `who`, including revoked and expired
+    /// ones.
+    pub fn attestations_of(env: Env, who: Address) -> Vec<Attestation> {
+        identities(&env).get(who).unwrap_or(Vec::new(&env))
+    }
+
+    pub fn is_verified(env: Env, who: Address) -> bool {
+        is_verified(&env, &who)
+    }
+}
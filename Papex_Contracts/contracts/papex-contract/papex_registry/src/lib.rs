@@ -1,14 +1,37 @@
 #![no_std]
 
 use core::option::Option;
+use papex_common::{fees, pause};
+use soroban_sdk::xdr::ToXdr;
 use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, Address, Env, Map, String, Vec,
+    contract, contractimpl, contracttype, symbol_short, Address, Bytes, BytesN, Env, Map, String,
+    Vec,
 };
 
 fn k_admin() -> soroban_sdk::Symbol { symbol_short!("adm") }
 fn k_next_id() -> soroban_sdk::Symbol { symbol_short!("nid") }
 fn k_papers() -> soroban_sdk::Symbol { symbol_short!("ppr") }
 fn k_author_index() -> soroban_sdk::Symbol { symbol_short!("aix") }
+fn k_pending_admin() -> soroban_sdk::Symbol { symbol_short!("padm") }
+fn k_admin_delay() -> soroban_sdk::Symbol { symbol_short!("adly") }
+fn k_revisions() -> soroban_sdk::Symbol { symbol_short!("rev") }
+fn k_content_index() -> soroban_sdk::Symbol { symbol_short!("cix") }
+fn k_embargoes() -> soroban_sdk::Symbol { symbol_short!("emb") }
+fn k_categories() -> soroban_sdk::Symbol { symbol_short!("cat") }
+fn k_category_index() -> soroban_sdk::Symbol { symbol_short!("catix") }
+fn k_tag_index() -> soroban_sdk::Symbol { symbol_short!("tagix") }
+fn k_token_licenses() -> soroban_sdk::Symbol { symbol_short!("tlic") }
+
+const MAX_TAGS: u32 = 8;
+const MAX_TAG_LEN: u32 = 32;
+
+mod funding;
+mod identity;
+pub use funding::{
+    Approval, Campaign, CampaignStatus, CampaignTerms, Milestone, MilestoneSpec, PaperToken,
+};
+pub use identity::{Attestation, IdentityKind};
+pub use papex_common::{FeeConfig, PauseController, PauseControllerClient};
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 #[contracttype]
@@ -16,6 +39,40 @@ pub enum PaperStatus {
     Pending,
     Tokenized,
     Archived,
+    Embargoed,
+}
+
+/// Distribution license of a paper. `Custom` carries the URI of the terms.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum License {
+    AllRightsReserved,
+    Custom(String),
+    CcByNc,
+    CcBySa,
+    CcBy,
+}
+
+impl License {
+    /// Rank used to allow only widening changes: all rights reserved, then
+    /// custom terms, CC-BY-NC, CC-BY-SA and finally CC-BY.
+    fn permissiveness(&self) -> u32 {
+        match self {
+            License::AllRightsReserved => 0,
+            License::Custom(_) => 1,
+            License::CcByNc => 2,
+            License::CcBySa => 3,
+            License::CcBy => 4,
+        }
+    }
+}
+
+/// SHA-256 digests of a paper's PDF and of its metadata JSON.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ContentHashes {
+    pub pdf: BytesN<32>,
+    pub metadata: BytesN<32>,
 }
 
 #[derive(Clone, Debug)]
@@ -27,6 +84,26 @@ pub struct Paper {
     pub token: Option<Address>,
     pub status: PaperStatus,
     pub registered_at: u64,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub license: License,
+}
+
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Revision {
+    pub metadata_uri: String,
+    pub content: ContentHashes,
+    pub revised_at: u64,
+}
+
+/// Commitment to a hidden metadata URI: `sha256(uri.to_xdr() || salt)`,
+/// where `uri.to_xdr()` is the XDR encoding of the URI as an `ScVal` string.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Embargo {
+    pub commitment: BytesN<32>,
+    pub unlock_at: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -34,11 +111,195 @@ pub struct Paper {
 pub struct PaperRecord {
     pub id: u32,
     pub data: Paper,
+    pub owner_verified: bool,
+}
+
+/// Admin handover waiting for the proposed address to accept it.
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct PendingAdmin {
+    pub admin: Address,
+    pub effective_at: u64,
 }
 
 #[contract]
 pub struct PapexRegistry;
 
+impl PapexRegistry {
+    fn read_admin(env: &Env) -> Address {
+        env.storage().instance().get(&k_admin()).unwrap()
+    }
+
+    fn require_admin(env: &Env, caller: &Address) {
+        caller.require_auth();
+        if *caller != Self::read_admin(env) {
+            panic!("only admin");
+        }
+    }
+
+    fn record(env: &Env, id: u32, data: Paper) -> PaperRecord {
+        PaperRecord {
+            id,
+            owner_verified: identity::is_verified(env, &data.owner),
+            data,
+        }
+    }
+
+    fn papers(env: &Env) -> Map<u32, Paper> {
+        env.storage()
+            .instance()
+            .get(&k_papers())
+            .unwrap_or(Map::new(env))
+    }
+
+    fn write_papers(env: &Env, papers: &Map<u32, Paper>) {
+        env.storage().instance().set(&k_papers(), papers);
+    }
+
+    /// Appends a revision for `paper_id`, rejecting a PDF or metadata hash
+    /// that is already anchored to a different paper.
+    fn anchor_content(env: &Env, paper_id: u32, metadata_uri: &String, content: &ContentHashes) {
+        let mut index: Map<BytesN<32>, u32> = env
+            .storage()
+            .instance()
+            .get(&k_content_index())
+            .unwrap_or(Map::new(env));
+        for hash in [&content.pdf, &content.metadata] {
+            if let Option::Some(existing) = index.get(hash.clone()) {
+                if existing != paper_id {
+                    panic!("duplicate content");
+                }
+            }
+            index.set(hash.clone(), paper_id);
+        }
+        env.storage().instance().set(&k_content_index(), &index);
+
+        let mut revisions: Map<u32, Vec<Revision>> = env
+            .storage()
+            .instance()
+            .get(&k_revisions())
+            .unwrap_or(Map::new(env));
+        let mut history = revisions.get(paper_id).unwrap_or(Vec::new(env));
+        history.push_back(Revision {
+            metadata_uri: metadata_uri.clone(),
+            content: content.clone(),
+            revised_at: env.ledger().timestamp(),
+        });
+        let revision = history.len() - 1;
+        revisions.set(paper_id, history);
+        env.storage().instance().set(&k_revisions(), &revisions);
+
+        env.events().publish(
+            (symbol_short!("anchor"),),
+            (paper_id, revision, content.pdf.clone()),
+        );
+    }
+
+    fn read_categories(env: &Env) -> Vec<String> {
+        env.storage()
+            .instance()
+            .get(&k_categories())
+            .unwrap_or(Vec::new(env))
+    }
+
+    fn index_paper(env: &Env, key: soroban_sdk::Symbol, entry: String, paper_id: u32) {
+        let mut index: Map<String, Vec<u32>> = env
+            .storage()
+            .instance()
+            .get(&key)
+            .unwrap_or(Map::new(env));
+        let mut ids = index.get(entry.clone()).unwrap_or(Vec::new(env));
+        ids.push_back(paper_id);
+        index.set(entry, ids);
+        env.storage().instance().set(&key, &index);
+    }
+
+    fn indexed_page(
+        env: &Env,
+        key: soroban_sdk::Symbol,
+        entry: String,
+        cursor: u32,
+        limit: u32,
+    ) -> Vec<PaperRecord> {
+        let index: Map<String, Vec<u32>> = env
+            .storage()
+            .instance()
+            .get(&key)
+            .unwrap_or(Map::new(env));
+        let ids = index.get(entry).unwrap_or(Vec::new(env));
+        let papers = Self::papers(env);
+        let mut out = Vec::new(env);
+        let mut position = cursor;
+        while position < ids.len() && (limit == 0 || out.len() < limit) {
+            let id = ids.get(position).unwrap();
+            if let Option::Some(data) = papers.get(id) {
+                out.push_back(Self::record(env, id, data));
+            }
+            position += 1;
+        }
+        out
+    }
+
+    fn ensure_valid_labels(env: &Env, category: &Option<String>, tags: &Vec<String>) {
+        if let Option::Some(code) = category {
+            if !Self::read_categories(env).contains(code) {
+                panic!("unknown category");
+            }
+        }
+        if tags.len() > MAX_TAGS {
+            panic!("too many tags");
+        }
+        for (i, tag) in tags.iter().enumerate() {
+            if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+                panic!("invalid tag");
+            }
+            if tags.first_index_of(&tag) != Option::Some(i as u32) {
+                panic!("duplicate tag");
+            }
+        }
+    }
+
+    fn index_labels(env: &Env, paper_id: u32, category: &Option<String>, tags: &Vec<String>) {
+        if let Option::Some(code) = category {
+            Self::index_paper(env, k_category_index(), code.clone(), paper_id);
+        }
+        for tag in tags.iter() {
+            Self::index_paper(env, k_tag_index(), tag, paper_id);
+        }
+    }
+
+    fn embargoes(env: &Env) -> Map<u32, Embargo> {
+        env.storage()
+            .instance()
+            .get(&k_embargoes())
+            .unwrap_or(Map::new(env))
+    }
+
+    fn ensure_not_embargoed(paper: &Paper) {
+        if paper.status == PaperStatus::Embargoed {
+            panic!("paper under embargo");
+        }
+    }
+
+    fn reveal_commitment(env: &Env, metadata_uri: &String, salt: &BytesN<32>) -> BytesN<32> {
+        let mut preimage = metadata_uri.clone().to_xdr(env);
+        preimage.append(&Bytes::from(salt.clone()));
+        env.crypto().sha256(&preimage).to_bytes()
+    }
+
+    /// Creative Commons licenses are tokenizable unless the admin says
+    /// otherwise.
+    fn default_token_licenses(env: &Env) -> Vec<License> {
+        Vec::from_array(env, [License::CcBy, License::CcBySa, License::CcByNc])
+    }
+
+    fn ensure_not_paused(env: &Env) {
+        if pause::paused(env) {
+            panic!("paused");
+        }
+    }
+}
+
 #[contractimpl]
 impl PapexRegistry {
     pub fn init(env: Env, admin: Address) {
@@ -52,26 +313,207 @@ impl PapexRegistry {
         env.storage()
             .instance()
             .set(&k_author_index(), &Map::<Address, Vec<u32>>::new(&env));
+        env.storage()
+            .instance()
+            .set(&k_token_licenses(), &Self::default_token_licenses(&env));
     }
 
     pub fn admin(env: Env) -> Address {
         env.storage().instance().get(&k_admin()).unwrap()
     }
 
+    /// Seconds a proposed admin has to wait before `accept_admin` succeeds.
+    pub fn admin_delay(env: Env) -> u64 {
+        env.storage().instance().get(&k_admin_delay()).unwrap_or(0)
+    }
+
+    pub fn set_admin_delay(env: Env, caller: Address, delay: u64) {
+        Self::require_admin(&env, &caller);
+        env.storage().instance().set(&k_admin_delay(), &delay);
+        env.events()
+            .publish((symbol_short!("adelay"),), (caller, delay));
+    }
+
+    pub fn pending_admin(env: Env) -> Option<PendingAdmin> {
+        env.storage().instance().get(&k_pending_admin())
+    }
+
+    /// First step of the handover: records `new_admin` as pending. A later
+    /// proposal replaces an earlier one.
+    pub fn propose_admin(env: Env, caller: Address, new_admin: Address) -> PendingAdmin {
+        Self::require_admin(&env, &caller);
+        let delay = Self::admin_delay(env.clone());
+        let pending = PendingAdmin {
+            admin: new_admin.clone(),
+            effective_at: env.ledger().timestamp().saturating_add(delay),
+        };
+        env.storage().instance().set(&k_pending_admin(), &pending);
+
+        env.events().publish(
+            (symbol_short!("adprop"),),
+            (caller, new_admin, pending.effective_at),
+        );
+
+        pending
+    }
+
+    pub fn cancel_admin_proposal(env: Env, caller: Address) {
+        Self::require_admin(&env, &caller);
+        let pending: PendingAdmin = env
+            .storage()
+            .instance()
+            .get(&k_pending_admin())
+            .unwrap_or_else(|| panic!("no pending admin"));
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events()
+            .publish((symbol_short!("adcncl"),), (caller, pending.admin));
+    }
+
+    /// Second step of the handover, signed by the proposed admin once the
+    /// timelock has elapsed.
+    pub fn accept_admin(env: Env, caller: Address) -> Address {
+        caller.require_auth();
+        let pending: PendingAdmin = env
+            .storage()
+            .instance()
+            .get(&k_pending_admin())
+            .unwrap_or_else(|| panic!("no pending admin"));
+        if caller != pending.admin {
+            panic!("not pending admin");
+        }
+        if env.ledger().timestamp() < pending.effective_at {
+            panic!("admin timelock active");
+        }
+
+        let previous = Self::read_admin(&env);
+        env.storage().instance().set(&k_admin(), &caller);
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events()
+            .publish((symbol_short!("adacc"),), (previous, caller.clone()));
+
+        caller
+    }
+
+    /// Gives up admin rights for good. The admin slot is pointed at the
+    /// registry's own address, which never signs admin calls.
+    pub fn renounce_admin(env: Env, caller: Address) {
+        Self::require_admin(&env, &caller);
+        env.storage()
+            .instance()
+            .set(&k_admin(), &env.current_contract_address());
+        env.storage().instance().remove(&k_pending_admin());
+
+        env.events().publish((symbol_short!("adren"),), (caller,));
+    }
+
+    /// True when this registry or its pause controller is paused.
+    pub fn is_paused(env: Env) -> bool {
+        pause::paused(&env)
+    }
+
+    pub fn pauser(env: Env) -> Option<Address> {
+        pause::pauser(&env)
+    }
+
+    pub fn pause_controller(env: Env) -> Option<Address> {
+        pause::controller(&env)
+    }
+
+    pub fn set_pauser(env: Env, caller: Address, pauser: Option<Address>) {
+        Self::require_admin(&env, &caller);
+        pause::set_pauser(&env, &caller, pauser);
+    }
+
+    /// Points the registry at a shared pause controller. The controller must
+    /// not consult this registry in turn.
+    pub fn set_pause_controller(env: Env, caller: Address, controller: Option<Address>) {
+        Self::require_admin(&env, &caller);
+        pause::set_controller(&env, &caller, controller);
+    }
+
+    /// Halts state-changing entry points. Callable by the admin or the pauser.
+    pub fn pause(env: Env, caller: Address) {
+        caller.require_auth();
+        let pauser = pause::pauser(&env);
+        if caller != Self::read_admin(&env) && Option::Some(caller.clone()) != pauser {
+            panic!("not authorized");
+        }
+        pause::set_paused(&env, true);
+        env.events().publish((symbol_short!("pause"),), (caller,));
+    }
+
+    pub fn unpause(env: Env, caller: Address) {
+        Self::require_admin(&env, &caller);
+        pause::set_paused(&env, false);
+        env.events().publish((symbol_short!("unpause"),), (caller,));
+    }
+
+    /// Fee charged for registering a paper, if any.
+    pub fn fees(env: Env) -> Option<FeeConfig> {
+        fees::config(&env)
+    }
+
+    pub fn set_fee(env: Env, caller: Address, fee: Option<FeeConfig>) {
+        Self::require_admin(&env, &caller);
+        fees::set_config(&env, fee);
+    }
+
+    pub fn is_fee_waived(env: Env, who: Address) -> bool {
+        fees::is_waived(&env, &who)
+    }
+
+    /// Exempts an allow-listed institution from the fee.
+    pub fn set_fee_waiver(env: Env, caller: Address, who: Address, waived: bool) {
+        Self::require_admin(&env, &caller);
+        fees::set_waiver(&env, &who, waived);
+    }
+
     pub fn next_id(env: Env) -> u32 {
         env.storage().instance().get(&k_next_id()).unwrap_or(0)
     }
 
+    /// Registers a paper. With `embargo` set, only the commitment is stored:
+    /// `metadata_uri`, `doi`, `category` and `tags` must be empty and are
+    /// filled in later by `reveal`. `category` must be one of the curated
+    /// `categories()`.
+    #[allow(clippy::too_many_arguments, clippy::len_zero)]
     pub fn register_paper(
         env: Env,
         caller: Address,
         metadata_uri: String,
         doi: Option<String>,
+        content: Option<ContentHashes>,
+        embargo: Option<Embargo>,
+        category: Option<String>,
+        tags: Vec<String>,
+        license: License,
     ) -> u32 {
         caller.require_auth();
-        if metadata_uri.len() == 0 {
-            panic!("metadata required");
+        Self::ensure_not_paused(&env);
+        Self::ensure_valid_labels(&env, &category, &tags);
+        match &embargo {
+            Option::Some(embargo) => {
+                if !metadata_uri.is_empty()
+                    || content.is_some()
+                    || doi.is_some()
+                    || category.is_some()
+                    || !tags.is_empty()
+                {
+                    panic!("embargo hides metadata");
+                }
+                if embargo.unlock_at <= env.ledger().timestamp() {
+                    panic!("invalid unlock time");
+                }
+            }
+            Option::None => {
+                if metadata_uri.len() == 0 {
+                    panic!("metadata required");
+                }
+            }
         }
+        fees::charge(&env, &caller);
 
         let mut next = env.storage().instance().get(&k_next_id()).unwrap_or(0u32);
         let paper_id = next;
@@ -84,10 +526,33 @@ impl PapexRegistry {
             metadata_uri,
             doi,
             token: None,
-            status: PaperStatus::Pending,
+            status: if embargo.is_some() {
+                PaperStatus::Embargoed
+            } else {
+                PaperStatus::Pending
+            },
             registered_at,
+            category: category.clone(),
+            tags: tags.clone(),
+            license,
         };
 
+        Self::index_labels(&env, paper_id, &category, &tags);
+
+        if let Option::Some(embargo) = embargo {
+            let mut embargoes = Self::embargoes(&env);
+            embargoes.set(paper_id, embargo.clone());
+            env.storage().instance().set(&k_embargoes(), &embargoes);
+            env.events().publish(
+                (symbol_short!("embargo"),),
+                (paper_id, embargo.commitment, embargo.unlock_at),
+            );
+        }
+
+        if let Option::Some(content) = content {
+            Self::anchor_content(&env, paper_id, &paper.metadata_uri, &content);
+        }
+
         let mut papers: Map<u32, Paper> = env
             .storage()
             .instance()
@@ -114,6 +579,174 @@ impl PapexRegistry {
         paper_id
     }
 
+    /// Same as `register_paper` without an embargo, but hashes the supplied
+    /// PDF and metadata bytes on chain.
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_paper_raw(
+        env: Env,
+        caller: Address,
+        metadata_uri: String,
+        doi: Option<String>,
+        pdf: Bytes,
+        metadata: Bytes,
+        category: Option<String>,
+        tags: Vec<String>,
+        license: License,
+    ) -> u32 {
+        let content = Self::hash_content(env.clone(), pdf, metadata);
+        Self::register_paper(
+            env,
+            caller,
+            metadata_uri,
+            doi,
+            Option::Some(content),
+            Option::None,
+            category,
+            tags,
+            license,
+        )
+    }
+
+    /// Opens an embargoed paper once its unlock time has passed. The URI and
+    /// salt must hash to the commitment given at registration; the DOI,
+    /// category and tags are published and indexed only now.
+    pub fn reveal(
+        env: Env,
+        paper_id: u32,
+        metadata_uri: String,
+        salt: BytesN<32>,
+        doi: Option<String>,
+        category: Option<String>,
+        tags: Vec<String>,
+    ) -> PaperRecord {
+        let mut papers = Self::papers(&env);
+        let mut paper = papers
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("paper not found"));
+        paper.owner.require_auth();
+
+        let mut embargoes = Self::embargoes(&env);
+        let embargo = embargoes
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("not embargoed"));
+        if env.ledger().timestamp() < embargo.unlock_at {
+            panic!("embargo active");
+        }
+        if metadata_uri.is_empty()
+            || Self::reveal_commitment(&env, &metadata_uri, &salt) != embargo.commitment
+        {
+            panic!("commitment mismatch");
+        }
+        Self::ensure_valid_labels(&env, &category, &tags);
+
+        embargoes.remove(paper_id);
+        env.storage().instance().set(&k_embargoes(), &embargoes);
+
+        Self::index_labels(&env, paper_id, &category, &tags);
+        paper.metadata_uri = metadata_uri;
+        paper.doi = doi;
+        paper.category = category;
+        paper.tags = tags;
+        paper.status = PaperStatus::Pending;
+        papers.set(paper_id, paper.clone());
+        Self::write_papers(&env, &papers);
+
+        env.events()
+            .publish((symbol_short!("reveal"),), (paper_id, paper.metadata_uri.clone()));
+
+        Self::record(&env, paper_id, paper)
+    }
+
+    /// Commitment and unlock time of a paper that has not been revealed yet.
+    pub fn embargo_of(env: Env, paper_id: u32) -> Option<Embargo> {
+        Self::embargoes(&env).get(paper_id)
+    }
+
+    /// Publishes a new version of the paper together with the hashes of its
+    /// PDF and metadata JSON. Only the paper owner may revise it.
+    pub fn revise_paper(
+        env: Env,
+        caller: Address,
+        paper_id: u32,
+        metadata_uri: String,
+        content: ContentHashes,
+    ) -> PaperRecord {
+        caller.require_auth();
+        Self::ensure_not_paused(&env);
+        if metadata_uri.is_empty() {
+            panic!("metadata required");
+        }
+
+        let mut papers = Self::papers(&env);
+        let mut paper = papers
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("paper not found"));
+        if caller != paper.owner {
+            panic!("only owner");
+        }
+        Self::ensure_not_embargoed(&paper);
+
+        Self::anchor_content(&env, paper_id, &metadata_uri, &content);
+        paper.metadata_uri = metadata_uri;
+        papers.set(paper_id, paper.clone());
+        Self::write_papers(&env, &papers);
+
+        Self::record(&env, paper_id, paper)
+    }
+
+    /// Same as `revise_paper`, but hashes the supplied PDF and metadata bytes
+    /// on chain.
+    pub fn revise_paper_raw(
+        env: Env,
+        caller: Address,
+        paper_id: u32,
+        metadata_uri: String,
+        pdf: Bytes,
+        metadata: Bytes,
+    ) -> PaperRecord {
+        let content = Self::hash_content(env.clone(), pdf, metadata);
+        Self::revise_paper(env, caller, paper_id, metadata_uri, content)
+    }
+
+    pub fn hash_content(env: Env, pdf: Bytes, metadata: Bytes) -> ContentHashes {
+        ContentHashes {
+            pdf: env.crypto().sha256(&pdf).to_bytes(),
+            metadata: env.crypto().sha256(&metadata).to_bytes(),
+        }
+    }
+
+    pub fn revisions(env: Env, paper_id: u32) -> Vec<Revision> {
+        let revisions: Map<u32, Vec<Revision>> = env
+            .storage()
+            .instance()
+            .get(&k_revisions())
+            .unwrap_or(Map::new(&env));
+        revisions.get(paper_id).unwrap_or(Vec::new(&env))
+    }
+
+    /// Hashes anchored by the latest revision.
+    pub fn content_of(env: Env, paper_id: u32) -> Option<ContentHashes> {
+        Self::revisions(env, paper_id).last().map(|rev| rev.content)
+    }
+
+    /// True if `hash` matches the PDF or metadata hash of any anchored
+    /// revision of the paper.
+    pub fn verify_content(env: Env, paper_id: u32, hash: BytesN<32>) -> bool {
+        Self::revisions(env, paper_id)
+            .iter()
+            .any(|rev| rev.content.pdf == hash || rev.content.metadata == hash)
+    }
+
+    /// Paper that a PDF or metadata hash is anchored to, if any.
+    pub fn paper_by_content(env: Env, hash: BytesN<32>) -> Option<u32> {
+        let index: Map<BytesN<32>, u32> = env
+            .storage()
+            .instance()
+            .get(&k_content_index())
+            .unwrap_or(Map::new(&env));
+        index.get(hash)
+    }
+
     pub fn set_token(
         env: Env,
         caller: Address,
@@ -136,6 +769,10 @@ impl PapexRegistry {
         if caller != admin && caller != paper.owner {
             panic!("not authorized");
         }
+        Self::ensure_not_embargoed(&paper);
+        if !Self::tokenizable_licenses(env.clone()).contains(&paper.license) {
+            panic!("license not tokenizable");
+        }
 
         paper.token = Option::Some(token.clone());
         paper.status = PaperStatus::Tokenized;
@@ -145,7 +782,7 @@ impl PapexRegistry {
         env.events()
             .publish((symbol_short!("token"),), (paper_id, token.clone()));
 
-        PaperRecord { id: paper_id, data: paper }
+        Self::record(&env, paper_id, paper)
     }
 
     pub fn update_status(
@@ -168,6 +805,11 @@ impl PapexRegistry {
         let mut paper = papers
             .get(paper_id)
             .unwrap_or_else(|| panic!("paper not found"));
+        // Embargoes only end through `reveal`.
+        Self::ensure_not_embargoed(&paper);
+        if status == PaperStatus::Embargoed {
+            panic!("invalid status");
+        }
 
         paper.status = status;
         papers.set(paper_id, paper.clone());
@@ -176,19 +818,111 @@ impl PapexRegistry {
         env.events()
             .publish((symbol_short!("pstat"),), (paper_id, status as u32));
 
-        PaperRecord { id: paper_id, data: paper }
+        Self::record(&env, paper_id, paper)
     }
 
+    /// Embargoed papers come back with an empty `metadata_uri`, no DOI and no
+    /// category or tags until revealed.
     pub fn get_paper(env: Env, paper_id: u32) -> Option<PaperRecord> {
         let papers: Map<u32, Paper> = env
             .storage()
             .instance()
             .get(&k_papers())
             .unwrap_or(Map::new(&env));
-        papers.get(paper_id).map(|data| PaperRecord {
-            id: paper_id,
-            data,
-        })
+        papers.get(paper_id).map(|data| Self::record(&env, paper_id, data))
+    }
+
+    /// Moves a paper to a more permissive license. Only the owner may do
+    /// this, and narrowing the terms is rejected.
+    pub fn set_license(env: Env, caller: Address, paper_id: u32, license: License) -> PaperRecord {
+        caller.require_auth();
+        let mut papers = Self::papers(&env);
+        let mut paper = papers
+            .get(paper_id)
+            .unwrap_or_else(|| panic!("paper not found"));
+        if caller != paper.owner {
+            panic!("only owner");
+        }
+        if license.permissiveness() <= paper.license.permissiveness() {
+            panic!("license can only widen");
+        }
+
+        paper.license = license.clone();
+        papers.set(paper_id, paper.clone());
+        Self::write_papers(&env, &papers);
+
+        env.events()
+            .publish((symbol_short!("license"),), (paper_id, license));
+
+        Self::record(&env, paper_id, paper)
+    }
+
+    /// Licenses under which `set_token` may tokenize a paper. Registries
+    /// deployed before the list existed get the defaults.
+    pub fn tokenizable_licenses(env: Env) -> Vec<License> {
+        env.storage()
+            .instance()
+            .get(&k_token_licenses())
+            .unwrap_or_else(|| Self::default_token_licenses(&env))
+    }
+
+    pub fn set_tokenizable_license(env: Env, caller: Address, license: License, allowed: bool) {
+        Self::require_admin(&env, &caller);
+        let mut list = Self::tokenizable_licenses(env.clone());
+        match list.first_index_of(&license) {
+            Option::Some(index) if !allowed => {
+                list.remove(index);
+            }
+            Option::None if allowed => list.push_back(license.clone()),
+            _ => {}
+        }
+        env.storage().instance().set(&k_token_licenses(), &list);
+        env.events()
+            .publish((symbol_short!("tlic"),), (license, allowed));
+    }
+
+    /// Curated field-of-study codes (e.g. arXiv `cs.LG`) accepted by
+    /// `register_paper`.
+    pub fn categories(env: Env) -> Vec<String> {
+        Self::read_categories(&env)
+    }
+
+    pub fn add_category(env: Env, caller: Address, code: String) {
+        Self::require_admin(&env, &caller);
+        if code.is_empty() || code.len() > MAX_TAG_LEN {
+            panic!("invalid category");
+        }
+        let mut list = Self::read_categories(&env);
+        if list.contains(&code) {
+            panic!("category exists");
+        }
+        list.push_back(code.clone());
+        env.storage().instance().set(&k_categories(), &list);
+        env.events().publish((symbol_short!("catadd"),), (code,));
+    }
+
+    /// Stops new papers from using `code`. Papers already filed under it stay
+    /// indexed.
+    pub fn remove_category(env: Env, caller: Address, code: String) {
+        Self::require_admin(&env, &caller);
+        let mut list = Self::read_categories(&env);
+        let index = list
+            .first_index_of(&code)
+            .unwrap_or_else(|| panic!("category not found"));
+        list.remove(index);
+        env.storage().instance().set(&k_categories(), &list);
+        env.events().publish((symbol_short!("catrm"),), (code,));
+    }
+
+    /// Papers filed under `code`, in registration order, starting at position
+    /// `cursor` of the category index. `limit == 0` returns the rest.
+    pub fn papers_in_category(env: Env, code: String, cursor: u32, limit: u32) -> Vec<PaperRecord> {
+        Self::indexed_page(&env, k_category_index(), code, cursor, limit)
+    }
+
+    /// Papers carrying `tag`, paged like `papers_in_category`.
+    pub fn papers_with_tag(env: Env, tag: String, cursor: u32, limit: u32) -> Vec<PaperRecord> {
+        Self::indexed_page(&env, k_tag_index(), tag, cursor, limit)
     }
 
     pub fn papers_of(env: Env, owner: Address) -> Vec<u32> {
@@ -214,7 +948,7 @@ impl PapexRegistry {
             }
             let id = entry;
             if let Option::Some(data) = papers.get(id) {
-                out.push_back(PaperRecord { id, data });
+                out.push_back(Self::record(&env, id, data));
                 count += 1;
             }
         }
@@ -3,10 +3,23 @@
 use super::*;
 use core::option::Option;
 use soroban_sdk::{
-    testutils::{Address as _},
-    Address, Env, String,
+    testutils::{Address as _, Ledger},
+    contract, contractimpl, token, vec, Address, Bytes, Env, String, Vec,
 };
 
+fn register_plain(client: &PapexRegistryClient, author: &Address, metadata: &String) -> u32 {
+    client.register_paper(
+        author,
+        metadata,
+        &Option::None,
+        &Option::None,
+        &Option::None,
+        &Option::None,
+        &Vec::new(&client.env),
+        &License::AllRightsReserved,
+    )
+}
+
 #[test]
 fn register_and_tokenize_paper() {
     let env = Env::default();
@@ -25,7 +38,16 @@ fn register_and_tokenize_paper() {
 
     let metadata = String::from_str(&env, "ipfs://paper/123");
     let doi = Option::Some(String::from_str(&env, "10.1234/example"));
-    let paper_id = client.register_paper(&author, &metadata, &doi);
+    let paper_id = client.register_paper(
+        &author,
+        &metadata,
+        &doi,
+        &Option::None,
+        &Option::None,
+        &Option::None,
+        &Vec::new(&env),
+        &License::CcBy,
+    );
 
     assert_eq!(paper_id, 0);
     assert_eq!(client.next_id(), 1);
@@ -52,3 +74,706 @@ fn register_and_tokenize_paper() {
     let archived = client.update_status(&admin, &paper_id, &PaperStatus::Archived);
     assert_eq!(archived.data.status, PaperStatus::Archived);
 }
+
+#[test]
+fn admin_handover_with_timelock_and_renounce() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let successor = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    client.set_admin_delay(&admin, &3_600);
+    let pending = client.propose_admin(&admin, &successor);
+    assert_eq!(pending.admin, successor);
+    assert_eq!(pending.effective_at, 3_600);
+
+    // Only the proposed address may accept, and only after the delay.
+    assert!(client.try_accept_admin(&admin).is_err());
+    assert!(client.try_accept_admin(&successor).is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 3_600);
+    assert_eq!(client.accept_admin(&successor), successor);
+    assert_eq!(client.admin(), successor);
+    assert!(client.pending_admin().is_none());
+    assert!(client.try_propose_admin(&admin, &admin).is_err());
+
+    client.renounce_admin(&successor);
+    assert_eq!(client.admin(), contract_id);
+    assert!(client.try_set_admin_delay(&successor, &0).is_err());
+}
+
+#[test]
+fn pause_blocks_registration_but_not_views() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let guardian = Address::generate(&env);
+    let author = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let metadata = String::from_str(&env, "ipfs://paper/1");
+    let paper_id = register_plain(&client, &author, &metadata);
+
+    assert!(client.try_pause(&author).is_err());
+    client.set_pauser(&admin, &Option::Some(guardian.clone()));
+    client.pause(&guardian);
+    assert!(client.is_paused());
+
+    assert!(client
+        .try_register_paper(
+            &author,
+            &metadata,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Vec::new(&env),
+            &License::AllRightsReserved,
+        )
+        .is_err());
+    assert!(client.get_paper(&paper_id).is_some());
+    assert_eq!(client.papers_of(&author).len(), 1);
+
+    // The guardian can halt the registry but only the admin resumes it.
+    assert!(client.try_unpause(&guardian).is_err());
+    client.unpause(&admin);
+    assert_eq!(register_plain(&client, &author, &metadata), 1);
+}
+
+#[test]
+fn content_hashes_are_anchored_per_revision() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let copycat = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let pdf_v1 = Bytes::from_slice(&env, b"%PDF-1.7 first draft");
+    let json_v1 = Bytes::from_slice(&env, b"{\"title\":\"Draft\"}");
+    let v1 = client.hash_content(&pdf_v1, &json_v1);
+    assert_eq!(v1.pdf, env.crypto().sha256(&pdf_v1).to_bytes());
+
+    let uri_v1 = String::from_str(&env, "ipfs://paper/v1");
+    let anchored = Option::Some(v1.clone());
+    let no_tags = Vec::new(&env);
+    let paper_id = client.register_paper(
+        &author,
+        &uri_v1,
+        &Option::None,
+        &anchored,
+        &Option::None,
+        &Option::None,
+        &no_tags,
+        &License::AllRightsReserved,
+    );
+    assert_eq!(client.content_of(&paper_id), Option::Some(v1.clone()));
+    assert_eq!(client.paper_by_content(&v1.pdf), Option::Some(paper_id));
+
+    // The same PDF cannot be registered again under another owner.
+    assert!(client
+        .try_register_paper(
+            &copycat,
+            &uri_v1,
+            &Option::None,
+            &anchored,
+            &Option::None,
+            &Option::None,
+            &no_tags,
+            &License::AllRightsReserved,
+        )
+        .is_err());
+
+    let uri_v2 = String::from_str(&env, "ipfs://paper/v2");
+    let pdf_v2 = Bytes::from_slice(&env, b"%PDF-1.7 camera ready");
+    assert!(client
+        .try_revise_paper_raw(&copycat, &paper_id, &uri_v2, &pdf_v2, &json_v1)
+        .is_err());
+    let revised = client.revise_paper_raw(&author, &paper_id, &uri_v2, &pdf_v2, &json_v1);
+    assert_eq!(revised.data.metadata_uri, uri_v2);
+    assert_eq!(client.content_of(&paper_id).unwrap().metadata, v1.metadata);
+
+    let revisions = client.revisions(&paper_id);
+    assert_eq!(revisions.len(), 2);
+    assert_eq!(revisions.get(1).unwrap().metadata_uri, uri_v2);
+
+    let v2_pdf = env.crypto().sha256(&pdf_v2).to_bytes();
+    assert!(client.verify_content(&paper_id, &v1.pdf));
+    assert!(client.verify_content(&paper_id, &v2_pdf));
+    assert!(client.verify_content(&paper_id, &v1.metadata));
+    assert!(!client.verify_content(&paper_id, &BytesN::from_array(&env, &[7; 32])));
+
+    // Raw registration checks the metadata hash too, not just the PDF.
+    let pdf_copy = Bytes::from_slice(&env, b"%PDF-1.7 lightly edited copy");
+    let json_own = Bytes::from_slice(&env, b"{\"title\":\"Own work\"}");
+    let license = License::AllRightsReserved;
+    assert!(client
+        .try_register_paper_raw(
+            &copycat, &uri_v1, &Option::None, &pdf_copy, &json_v1, &Option::None, &no_tags,
+            &license,
+        )
+        .is_err());
+    let own_id = client.register_paper_raw(
+        &copycat, &uri_v1, &Option::None, &pdf_copy, &json_own, &Option::None, &no_tags,
+        &license,
+    );
+    let own_metadata = env.crypto().sha256(&json_own).to_bytes();
+    assert_eq!(client.paper_by_content(&own_metadata), Option::Some(own_id));
+}
+
+#[test]
+fn registration_fee_is_collected_unless_waived() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let author = Address::generate(&env);
+    let institution = Address::generate(&env);
+
+    let asset = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    let asset_admin = token::StellarAssetClient::new(&env, &asset);
+    let asset_client = token::Client::new(&env, &asset);
+    asset_admin.mint(&author, &1_000);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+    assert!(client.fees().is_none());
+
+    let fee = FeeConfig {
+        asset: asset.clone(),
+        amount: 250,
+        treasury: treasury.clone(),
+    };
+    client.set_fee(&admin, &Option::Some(fee));
+    assert_eq!(client.fees().unwrap().amount, 250);
+
+    let metadata = String::from_str(&env, "ipfs://paper/fee");
+    register_plain(&client, &author, &metadata);
+    assert_eq!(asset_client.balance(&author), 750);
+    assert_eq!(asset_client.balance(&treasury), 250);
+
+    // Allow-listed institutions register for free; others without funds cannot.
+    assert!(client
+        .try_register_paper(
+            &institution,
+            &metadata,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Vec::new(&env),
+            &License::AllRightsReserved,
+        )
+        .is_err());
+    client.set_fee_waiver(&admin, &institution, &true);
+    assert!(client.is_fee_waived(&institution));
+    register_plain(&client, &institution, &metadata);
+    assert_eq!(asset_client.balance(&treasury), 250);
+}
+
+#[test]
+fn embargoed_paper_reveals_after_unlock() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let genomics = String::from_str(&env, "genomics");
+    client.add_category(&admin, &genomics);
+
+    // Longer than any fixed-size hashing buffer would allow.
+    let mut long_uri = [b'a'; 400];
+    long_uri[..13].copy_from_slice(b"ipfs://paper/");
+    let uri = String::from_bytes(&env, &long_uri);
+    let salt = BytesN::from_array(&env, &[9; 32]);
+    let mut preimage = uri.clone().to_xdr(&env);
+    preimage.append(&Bytes::from(salt.clone()));
+    let embargo = Embargo {
+        commitment: env.crypto().sha256(&preimage).to_bytes(),
+        unlock_at: 1_000,
+    };
+
+    // Nothing but the commitment may be public before the reveal.
+    let empty = String::from_str(&env, "");
+    let no_tags = Vec::new(&env);
+    let doi = Option::Some(String::from_str(&env, "10.1234/secret"));
+    let category = Option::Some(genomics.clone());
+    let tags = vec![&env, String::from_str(&env, "crispr")];
+    let embargoed = Option::Some(embargo.clone());
+    let license = License::AllRightsReserved;
+    assert!(client
+        .try_register_paper(
+            &author, &empty, &doi, &Option::None, &embargoed, &Option::None, &no_tags, &license,
+        )
+        .is_err());
+    assert!(client
+        .try_register_paper(
+            &author, &empty, &Option::None, &Option::None, &embargoed, &category, &no_tags,
+            &license,
+        )
+        .is_err());
+    assert!(client
+        .try_register_paper(
+            &author, &empty, &Option::None, &Option::None, &embargoed, &Option::None, &tags,
+            &license,
+        )
+        .is_err());
+    let paper_id = client.register_paper(
+        &author,
+        &empty,
+        &Option::None,
+        &Option::None,
+        &embargoed,
+        &Option::None,
+        &no_tags,
+        &license,
+    );
+    let hidden = client.get_paper(&paper_id).unwrap();
+    assert_eq!(hidden.data.status, PaperStatus::Embargoed);
+    assert!(hidden.data.metadata_uri.is_empty());
+    assert_eq!(client.embargo_of(&paper_id).unwrap().unlock_at, 1_000);
+    assert!(client
+        .try_set_token(&author, &paper_id, &Address::generate(&env))
+        .is_err());
+    assert!(client
+        .try_update_status(&admin, &paper_id, &PaperStatus::Pending)
+        .is_err());
+
+    assert!(client
+        .try_reveal(&paper_id, &uri, &salt, &doi, &category, &tags)
+        .is_err());
+
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let wrong = String::from_str(&env, "ipfs://paper/other");
+    assert!(client
+        .try_reveal(&paper_id, &wrong, &salt, &doi, &category, &tags)
+        .is_err());
+
+    let revealed = client.reveal(&paper_id, &uri, &salt, &doi, &category, &tags);
+    assert_eq!(revealed.data.metadata_uri, uri);
+    assert_eq!(revealed.data.doi, doi);
+    assert_eq!(revealed.data.tags, tags);
+    assert_eq!(revealed.data.status, PaperStatus::Pending);
+    assert_eq!(revealed.data.registered_at, 0);
+    assert!(client.embargo_of(&paper_id).is_none());
+    assert_eq!(client.papers_in_category(&genomics, &0, &0).len(), 1);
+    assert!(client
+        .try_update_status(&admin, &paper_id, &PaperStatus::Embargoed)
+        .is_err());
+}
+
+#[test]
+fn attested_owner_is_flagged_as_verified() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let orcid_bridge = Address::generate(&env);
+    let author = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let paper_id = register_plain(&client, &author, &String::from_str(&env, "ipfs://p"));
+    assert!(!client.get_paper(&paper_id).unwrap().owner_verified);
+
+    let orcid = String::from_str(&env, "0000-0002-1825-0097");
+    assert!(client
+        .try_attest(&orcid_bridge, &author, &IdentityKind::Orcid, &orcid, &0)
+        .is_err());
+
+    client.set_attester(&admin, &orcid_bridge, &true);
+    client.attest(&orcid_bridge, &author, &IdentityKind::Orcid, &orcid, &500);
+    let identity = client.identity_of(&author);
+    assert_eq!(identity.len(), 1);
+    assert_eq!(identity.get(0).unwrap().value, orcid);
+    assert!(client.get_paper(&paper_id).unwrap().owner_verified);
+
+    // Expired attestations no longer count.
+    env.ledger().with_mut(|li| li.timestamp = 500);
+    assert!(!client.is_verified(&author));
+    assert!(!client.list_papers(&0).get(0).unwrap().owner_verified);
+
+    client.attest(&orcid_bridge, &author, &IdentityKind::Orcid, &orcid, &0);
+    assert!(client.is_verified(&author));
+    assert_eq!(client.attestations_of(&author).len(), 1);
+
+    client.revoke_attestation(&author, &author, &orcid_bridge, &IdentityKind::Orcid);
+    assert!(client.identity_of(&author).is_empty());
+    assert!(!client.get_paper(&paper_id).unwrap().owner_verified);
+    assert!(client.attestations_of(&author).get(0).unwrap().revoked);
+}
+
+#[test]
+fn papers_are_indexed_by_category_and_tag() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let genomics = String::from_str(&env, "q-bio.GN");
+    let ml = String::from_str(&env, "cs.LG");
+    let crispr = String::from_str(&env, "crispr");
+    let uri = String::from_str(&env, "ipfs://paper");
+    let register = |category: &String, tags: &Vec<String>| {
+        client.register_paper(
+            &author,
+            &uri,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Option::Some(category.clone()),
+            tags,
+            &License::CcBy,
+        )
+    };
+
+    // Only curated categories are accepted.
+    assert!(client
+        .try_register_paper(
+            &author,
+            &uri,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Option::Some(genomics.clone()),
+            &Vec::new(&env),
+            &License::AllRightsReserved,
+        )
+        .is_err());
+    client.add_category(&admin, &genomics);
+    client.add_category(&admin, &ml);
+    assert_eq!(client.categories().len(), 2);
+
+    let first = register(&genomics, &vec![&env, crispr.clone()]);
+    register(&ml, &Vec::new(&env));
+    let third = register(&genomics, &vec![&env, crispr.clone(), String::from_str(&env, "rna")]);
+    let dup = vec![&env, crispr.clone(), crispr.clone()];
+    assert!(client
+        .try_register_paper(
+            &author,
+            &uri,
+            &Option::None,
+            &Option::None,
+            &Option::None,
+            &Option::Some(genomics.clone()),
+            &dup,
+            &License::CcBy,
+        )
+        .is_err());
+
+    let page = client.papers_in_category(&genomics, &0, &1);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().id, first);
+    let next = client.papers_in_category(&genomics, &1, &10);
+    assert_eq!(next.len(), 1);
+    assert_eq!(next.get(0).unwrap().id, third);
+    assert_eq!(next.get(0).unwrap().data.category, Option::Some(genomics.clone()));
+
+    assert_eq!(client.papers_with_tag(&crispr, &0, &0).len(), 2);
+
+    client.remove_category(&admin, &genomics);
+    assert!(client.try_add_category(&author, &genomics).is_err());
+    assert_eq!(client.papers_in_category(&genomics, &0, &0).len(), 2);
+}
+
+#[test]
+fn license_only_widens_and_gates_tokenization() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let token_contract = Address::generate(&env);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+
+    let paper_id = register_plain(&client, &author, &String::from_str(&env, "ipfs://p"));
+    assert_eq!(
+        client.get_paper(&paper_id).unwrap().data.license,
+        License::AllRightsReserved
+    );
+
+    let defaults = vec![&env, License::CcBy, License::CcBySa, License::CcByNc];
+    assert_eq!(client.tokenizable_licenses(), defaults);
+    client.set_tokenizable_license(&admin, &License::CcBySa, &true);
+    client.set_tokenizable_license(&admin, &License::CcBy, &false);
+    client.set_tokenizable_license(&admin, &License::CcByNc, &false);
+    assert_eq!(client.tokenizable_licenses(), vec![&env, License::CcBySa]);
+    assert!(client.try_set_token(&author, &paper_id, &token_contract).is_err());
+
+    let terms = License::Custom(String::from_str(&env, "https://lab.example/terms"));
+    client.set_license(&author, &paper_id, &terms);
+    client.set_license(&author, &paper_id, &License::CcBySa);
+    assert!(client
+        .try_set_license(&author, &paper_id, &License::CcByNc)
+        .is_err());
+    assert!(client.try_set_license(&author, &paper_id, &terms).is_err());
+    assert!(client
+        .try_set_license(&admin, &paper_id, &License::CcBy)
+        .is_err());
+
+    let tokenized = client.set_token(&author, &paper_id, &token_contract);
+    assert_eq!(tokenized.data.license, License::CcBySa);
+    assert_eq!(tokenized.data.status, PaperStatus::Tokenized);
+}
+
+#[contract]
+struct MockPaperToken;
+
+#[contractimpl]
+impl MockPaperToken {
+    /// Records `amount` as `who`'s balance from the current ledger on.
+    pub fn set_balance(env: Env, who: Address, amount: i128) {
+        let now = env.ledger().sequence();
+        let previous = Self::balance_at(env.clone(), who.clone(), now + 1);
+        let supply = Self::total_supply_at(env.clone(), now + 1);
+        let mut balances: Vec<(u32, i128)> =
+            env.storage().instance().get(&who).unwrap_or(Vec::new(&env));
+        balances.push_back((now, amount));
+        env.storage().instance().set(&who, &balances);
+        let mut supplies: Vec<(u32, i128)> = env
+            .storage()
+            .instance()
+            .get(&symbol_short!("tot"))
+            .unwrap_or(Vec::new(&env));
+        supplies.push_back((now, supply - previous + amount));
+        env.storage().instance().set(&symbol_short!("tot"), &supplies);
+    }
+
+    pub fn balance_at(env: Env, who: Address, ledger: u32) -> i128 {
+        let balances = env.storage().instance().get(&who).unwrap_or(Vec::new(&env));
+        value_before(balances, ledger)
+    }
+
+    pub fn total_supply_at(env: Env, ledger: u32) -> i128 {
+        let supplies = env
+            .storage()
+            .instance()
+            .get(&symbol_short!("tot"))
+            .unwrap_or(Vec::new(&env));
+        value_before(supplies, ledger)
+    }
+}
+
+/// Last value recorded in a ledger before `ledger`, or zero.
+fn value_before(history: Vec<(u32, i128)>, ledger: u32) -> i128 {
+    history
+        .iter()
+        .filter(|(at, _)| *at < ledger)
+        .last()
+        .map_or(0, |(_, value)| value)
+}
+
+fn milestones(env: &Env, amounts: &[i128]) -> Vec<MilestoneSpec> {
+    let mut out = Vec::new(env);
+    for amount in amounts {
+        out.push_back(MilestoneSpec {
+            description: String::from_str(env, "deliverable"),
+            amount: *amount,
+        });
+    }
+    out
+}
+
+#[test]
+fn reviewed_campaign_releases_milestones_and_refunds_failures() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let backer = Address::generate(&env);
+    let reviewer_a = Address::generate(&env);
+    let reviewer_b = Address::generate(&env);
+
+    let asset = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    token::StellarAssetClient::new(&env, &asset).mint(&backer, &1_000);
+    let asset_client = token::Client::new(&env, &asset);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+    let paper_id = register_plain(&client, &author, &String::from_str(&env, "ipfs://p"));
+
+    let reviewers = vec![&env, reviewer_a.clone(), reviewer_b.clone()];
+    let terms = CampaignTerms {
+        asset: asset.clone(),
+        goal: 600,
+        deadline: 100,
+        milestones: milestones(&env, &[400, 200]),
+        approval: Approval::Reviewers(reviewers.clone(), 2),
+    };
+    assert!(client.try_open_campaign(&backer, &paper_id, &terms).is_err());
+    let funded = client.open_campaign(&author, &paper_id, &terms);
+
+    client.pledge(&backer, &funded, &500);
+    assert!(client.try_pledge(&backer, &funded, &200).is_err());
+    let campaign = client.pledge(&backer, &funded, &100);
+    assert_eq!(campaign.status, CampaignStatus::Funded);
+    assert_eq!(asset_client.balance(&contract_id), 600);
+    assert!(client.try_refund(&backer, &funded).is_err());
+
+    client.approve_milestone(&reviewer_a, &funded);
+    assert!(client.try_release_milestone(&funded).is_err());
+    assert!(client.try_approve_milestone(&backer, &funded).is_err());
+    client.approve_milestone(&reviewer_b, &funded);
+    client.release_milestone(&funded);
+    assert_eq!(asset_client.balance(&author), 400);
+
+    client.approve_milestone(&reviewer_a, &funded);
+    client.approve_milestone(&reviewer_b, &funded);
+    client.release_milestone(&funded);
+    assert_eq!(asset_client.balance(&author), 600);
+    assert_eq!(client.get_campaign(&funded).unwrap().status, CampaignStatus::Completed);
+
+    // A campaign that misses its goal refunds its backers after the deadline.
+    let short = client.open_campaign(
+        &author,
+        &paper_id,
+        &CampaignTerms {
+            deadline: 200,
+            ..terms.clone()
+        },
+    );
+    client.pledge(&backer, &short, &300);
+    assert!(client.try_refund(&backer, &short).is_err());
+    env.ledger().with_mut(|li| li.timestamp = 200);
+    assert!(client.try_pledge(&backer, &short, &1).is_err());
+    assert_eq!(client.refund(&backer, &short), 300);
+    assert_eq!(asset_client.balance(&backer), 400);
+    assert_eq!(client.get_campaign(&short).unwrap().status, CampaignStatus::Failed);
+
+    // A funded campaign that stops releasing milestones refunds the rest of
+    // its escrow once it stalls.
+    let stalled = client.open_campaign(
+        &author,
+        &paper_id,
+        &CampaignTerms {
+            goal: 300,
+            deadline: 1_000,
+            milestones: milestones(&env, &[200, 100]),
+            ..terms
+        },
+    );
+    client.pledge(&backer, &stalled, &300);
+    client.approve_milestone(&reviewer_a, &stalled);
+    client.approve_milestone(&reviewer_b, &stalled);
+    client.release_milestone(&stalled);
+    assert_eq!(asset_client.balance(&author), 800);
+    env.ledger().with_mut(|li| li.timestamp = 200 + 180 * 86_400 - 1);
+    assert!(client.try_refund(&backer, &stalled).is_err());
+    env.ledger().with_mut(|li| li.timestamp = 200 + 180 * 86_400);
+    assert!(client.try_approve_milestone(&reviewer_a, &stalled).is_err());
+    assert_eq!(client.refund(&backer, &stalled), 100);
+    assert_eq!(asset_client.balance(&backer), 200);
+    assert_eq!(client.get_campaign(&stalled).unwrap().status, CampaignStatus::Failed);
+}
+
+#[test]
+fn holder_vote_releases_milestone_with_quorum() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let author = Address::generate(&env);
+    let backer = Address::generate(&env);
+    let whale = Address::generate(&env);
+    let minnow = Address::generate(&env);
+
+    let asset = env.register_stellar_asset_contract_v2(admin.clone()).address();
+    token::StellarAssetClient::new(&env, &asset).mint(&backer, &1_000);
+
+    let paper_token = env.register_contract(None, MockPaperToken);
+    let holders = MockPaperTokenClient::new(&env, &paper_token);
+    holders.set_balance(&whale, &70);
+    holders.set_balance(&minnow, &30);
+
+    let contract_id = env.register_contract(None, PapexRegistry);
+    let client = PapexRegistryClient::new(&env, &contract_id);
+    client.init(&admin);
+    client.set_tokenizable_license(&admin, &License::AllRightsReserved, &true);
+    let paper_id = register_plain(&client, &author, &String::from_str(&env, "ipfs://p"));
+    client.set_token(&author, &paper_id, &paper_token);
+
+    let terms = CampaignTerms {
+        asset: asset.clone(),
+        goal: 500,
+        deadline: 1_000,
+        milestones: milestones(&env, &[500]),
+        approval: Approval::HolderVote(0, 60),
+    };
+    assert!(client.try_open_campaign(&author, &paper_id, &terms).is_err());
+    let terms = CampaignTerms {
+        approval: Approval::HolderVote(5_000, 60),
+        ..terms
+    };
+    let campaign_id = client.open_campaign(&author, &paper_id, &terms);
+    client.pledge(&backer, &campaign_id, &500);
+
+    // Pointing the paper at a token the owner controls changes no votes.
+    let rigged = env.register_contract(None, MockPaperToken);
+    MockPaperTokenClient::new(&env, &rigged).set_balance(&author, &1_000);
+    client.set_token(&author, &paper_id, &rigged);
+
+    env.ledger().with_mut(|li| li.sequence_number = 1);
+    client.request_release(&author, &campaign_id);
+    client.vote_milestone(&minnow, &campaign_id, &true);
+    assert!(client.try_vote_milestone(&minnow, &campaign_id, &true).is_err());
+    assert!(client.try_release_milestone(&campaign_id).is_err());
+
+    // Tokens moved after the vote opened carry no weight for the receiver.
+    let sybil = Address::generate(&env);
+    holders.set_balance(&minnow, &0);
+    holders.set_balance(&sybil, &30);
+    assert!(client.try_vote_milestone(&sybil, &campaign_id, &true).is_err());
+
+    // Turnout of 30% misses the 50% quorum, so the owner reopens the vote.
+    env.ledger().with_mut(|li| {
+        li.timestamp = 60;
+        li.sequence_number = 2;
+    });
+    assert!(client.try_release_milestone(&campaign_id).is_err());
+    client.request_release(&author, &campaign_id);
+    client.vote_milestone(&whale, &campaign_id, &true);
+    client.vote_milestone(&sybil, &campaign_id, &false);
+    assert!(client.try_vote_milestone(&minnow, &campaign_id, &false).is_err());
+
+    // Supply minted after the vote opened does not raise the quorum.
+    holders.set_balance(&Address::generate(&env), &1_000);
+
+    env.ledger().with_mut(|li| li.timestamp = 120);
+    let released = client.release_milestone(&campaign_id);
+    assert!(released.released);
+    assert_eq!(released.votes_for, 70);
+    assert_eq!(token::Client::new(&env, &asset).balance(&author), 500);
+    assert!(client.try_vote_milestone(&whale, &campaign_id, &true).is_err());
+}
@@ -415,6 +415,15 @@ export const ArticaProvider: React.FC<PropsWithChildren> = ({ children }) => {
         toAddress(caller).toScVal(),
         nativeToScVal(trimmedUri, { type: 'string' }),
         doiScVal,
+        // content: Option<ContentHashes> - hashes can be anchored later via revise_paper
+        xdr.ScVal.scvVoid(),
+        // embargo: Option<Embargo> - papers are registered publicly from the UI
+        xdr.ScVal.scvVoid(),
+        // category: Option<String> and tags: Vec<String>
+        xdr.ScVal.scvVoid(),
+        xdr.ScVal.scvVec([]),
+        // license: License - CC-BY, one of the licenses the registry tokenizes by default
+        xdr.ScVal.scvVec([xdr.ScVal.scvSymbol('CcBy')]),
       ];
 
       const { signTransaction: freighterSignTransaction } = await import('@stellar/freighter-api');